@@ -0,0 +1,149 @@
+use std::{collections::HashMap, fmt};
+use chrono::{Datelike as _, NaiveDate};
+use crate::{Error, VideoItem, VideoWorkMedium, WatchStatus, Watchlist};
+
+/// the earliest year a video work can plausibly be from
+const FIRST_YEAR: u16 = 1870;
+/// how far ahead of today an announced work can plausibly be dated
+const YEARS_AHEAD: u16 = 10;
+
+/// something wrong with one line of a data file, found by `Watchlist::check`
+#[derive(Debug)]
+pub struct Problem {
+    /// counts from 1
+    pub line_number: usize,
+    pub kind: ProblemKind,
+}
+
+#[derive(Debug)]
+pub enum ProblemKind {
+    /// the line is not a valid RON `VideoItem`; `column` counts from 1
+    Syntax { column: usize, message: String },
+    MovieWithPosition,
+    OngoingMovie,
+    /// an unwatched or planned series
+    VirginWithPosition,
+    /// a caught up or completed series
+    WatchedWithoutPosition,
+    /// only an ongoing series can be caught up
+    CaughtUpNotOngoing,
+    /// an ongoing series can only be caught up
+    CompletedButOngoing,
+    /// the same title, year and medium as the item on `first_line`
+    Duplicate { first_line: usize },
+    /// the same id as the item on `first_line`
+    DuplicateId { id: u32, first_line: usize },
+    UpdatedInFuture(NaiveDate),
+    ImplausibleYear(u16),
+    /// ratings are from 1 to 10; `season` is `None` for the rating of the whole work
+    RatingOutOfRange { season: Option<u16>, rating: u8 },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ProblemKind::Syntax { column, message } => write!(f, "line {}, column {column}: {message}", self.line_number),
+            kind => write!(f, "line {}: {kind}", self.line_number),
+        }
+    }
+}
+
+impl fmt::Display for ProblemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax { message, .. } => f.write_str(message),
+            Self::MovieWithPosition => f.write_str("movie has a watch position"),
+            Self::OngoingMovie => f.write_str("movie is marked ongoing"),
+            Self::VirginWithPosition => f.write_str("unwatched item has a watch position"),
+            Self::WatchedWithoutPosition => f.write_str("watched series has no watch position"),
+            Self::CaughtUpNotOngoing => f.write_str("caught up, but not an ongoing series (should be completed)"),
+            Self::CompletedButOngoing => f.write_str("completed, but the series is ongoing (should be caught up)"),
+            Self::Duplicate { first_line } => write!(f, "same title, year and medium as line {first_line}"),
+            Self::DuplicateId { id, first_line } => write!(f, "id {id} is already used on line {first_line}"),
+            Self::UpdatedInFuture(updated) => write!(f, "updated date {updated} is in the future"),
+            Self::ImplausibleYear(year) => write!(f, "implausible year {year}"),
+            Self::RatingOutOfRange { season: None, rating } => write!(f, "rating {rating} is not from 1 to 10"),
+            Self::RatingOutOfRange { season: Some(season), rating } => write!(f, "season {season} rating {rating} is not from 1 to 10"),
+        }
+    }
+}
+
+impl Watchlist {
+    /// every syntax error and broken invariant in the data file, in line order
+    pub fn check(&self, today: NaiveDate) -> Result<Vec<Problem>, Error> {
+        let mut problems = Vec::new();
+        let mut works: HashMap<(String, u16, VideoWorkMedium), usize> = HashMap::new();
+        let mut ids: HashMap<u32, usize> = HashMap::new();
+        let mut video_items = self.iter()?;
+        while let Some(video_item) = video_items.next() {
+            let line_number = video_items.line_number();
+            let video_item = match video_item {
+                Err(Error::Parse { error, .. }) => {
+                    let kind = ProblemKind::Syntax { column: error.span.start.col, message: error.code.to_string() };
+                    problems.push(Problem { line_number, kind });
+                    continue;
+                },
+                video_item => video_item?,
+            };
+            let work = (video_item.work.title.clone(), video_item.work.year, video_item.work.medium.clone());
+            if let Some(&first_line) = works.get(&work) {
+                problems.push(Problem { line_number, kind: ProblemKind::Duplicate { first_line } });
+            } else {
+                works.insert(work, line_number);
+            }
+            // items from before ids existed all have id 0 until it is assigned
+            if video_item.id != 0 {
+                if let Some(&first_line) = ids.get(&video_item.id) {
+                    problems.push(Problem { line_number, kind: ProblemKind::DuplicateId { id: video_item.id, first_line } });
+                } else {
+                    ids.insert(video_item.id, line_number);
+                }
+            }
+            problems.extend(item_problems(&video_item, today).into_iter().map(|kind| Problem { line_number, kind }));
+        }
+        Ok(problems)
+    }
+}
+
+fn item_problems(video_item: &VideoItem, today: NaiveDate) -> Vec<ProblemKind> {
+    let mut problems = Vec::new();
+    let position = video_item.watch_data.position.is_some();
+    if let VideoWorkMedium::Movie = video_item.work.medium {
+        if position {
+            problems.push(ProblemKind::MovieWithPosition);
+        }
+        if video_item.ongoing {
+            problems.push(ProblemKind::OngoingMovie);
+        }
+    }
+    match (&video_item.watch_data.status, &video_item.work.medium) {
+        (WatchStatus::Virgin | WatchStatus::PlanToWatch, VideoWorkMedium::TvShow | VideoWorkMedium::Anime) if position => {
+            problems.push(ProblemKind::VirginWithPosition);
+        },
+        (WatchStatus::CaughtUp | WatchStatus::Completed, VideoWorkMedium::TvShow | VideoWorkMedium::Anime) if !position => {
+            problems.push(ProblemKind::WatchedWithoutPosition);
+        },
+        _ => (),
+    }
+    let ongoing_series = video_item.ongoing && !matches!(video_item.work.medium, VideoWorkMedium::Movie);
+    match (&video_item.watch_data.status, ongoing_series) {
+        (WatchStatus::CaughtUp, false) => problems.push(ProblemKind::CaughtUpNotOngoing),
+        (WatchStatus::Completed, true) => problems.push(ProblemKind::CompletedButOngoing),
+        _ => (),
+    }
+    if video_item.updated > today {
+        problems.push(ProblemKind::UpdatedInFuture(video_item.updated));
+    }
+    let last_year = u16::try_from(today.year()).unwrap_or(u16::MAX).saturating_add(YEARS_AHEAD);
+    if !(FIRST_YEAR..=last_year).contains(&video_item.work.year) {
+        problems.push(ProblemKind::ImplausibleYear(video_item.work.year));
+    }
+    let ratings = video_item.opinion.rating.map(|rating| (None, rating)).into_iter()
+        .chain(video_item.opinion.season_ratings.iter().map(|(&season, &rating)| (Some(season), rating)));
+    for (season, rating) in ratings {
+        if !(1..=10).contains(&rating) {
+            problems.push(ProblemKind::RatingOutOfRange { season, rating });
+        }
+    }
+    problems
+}
@@ -0,0 +1,122 @@
+use crate::VideoItem;
+
+/// the data file format written by this version; bump it whenever the serialized shape of an item
+/// changes, even by an added field, so that older versions refuse the file instead of dropping what
+/// they do not know on their next rewrite, and teach `parse_item` to read the previous one if need be
+///
+/// 1. the original layout
+/// 2. `Exhausted` split into `CaughtUp` and `Completed`
+/// 3. `PlanToWatch`, `Rewatching`, `OnHold` and `Dropped` added; version 2 files read as is
+/// 4. `work.episodes` added; older files read as is, without episode counts
+/// 5. `history` added; older files read as is, with no history
+/// 6. `opinion` (ratings and review) added; older files read as is, unrated
+/// 7. `tags` added; older files read as is, untagged
+/// 8. `priority` added; older files read as is, with nothing queued
+/// 9. an ids line after the header; older files read as is, reusing the ids of removed items
+///
+/// fields are added with `#[serde(default)]` so that older files still read, but unknown fields
+/// are rejected: an item from a newer version is carried through rewrites as an unparseable line
+/// rather than losing them
+pub const CURRENT_VERSION: u32 = 9;
+
+/// files from before versioning have no header line and are version 1
+pub(crate) const UNVERSIONED: u32 = 1;
+
+/// the first line of a versioned data file, e.g. `(format:1)`
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Header {
+    format: u32,
+}
+
+/// the version a header line declares; `None` if the line is not a header (but e.g. an item)
+pub(crate) fn parse_header(line: &str) -> Option<u32> {
+    ron::from_str::<Header>(line).ok().map(|header| header.format)
+}
+
+pub(crate) fn header_line() -> String {
+    let mut line = ron::to_string(&Header { format: CURRENT_VERSION }).expect("a header always serializes");
+    line.push('\n');
+    line
+}
+
+/// the line after the header, e.g. `(next_id:13)`; every id below `next_id` has been given out,
+/// even if its item has since been removed, so that an id never comes to mean another item
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Ids {
+    next_id: u32,
+}
+
+/// the next id an ids line declares; `None` if the line is not one (but e.g. an item)
+pub(crate) fn parse_ids(line: &str) -> Option<u32> {
+    ron::from_str::<Ids>(line).ok().map(|ids| ids.next_id)
+}
+
+pub(crate) fn ids_line(next_id: u32) -> String {
+    let mut line = ron::to_string(&Ids { next_id }).expect("an ids line always serializes");
+    line.push('\n');
+    line
+}
+
+/// reads an item written in format `version`, converting it to the current shape
+pub(crate) fn parse_item(version: u32, line: &str) -> Result<VideoItem, ron::error::SpannedError> {
+    match version {
+        1 => ron::from_str::<v1::VideoItem>(line).map(VideoItem::from),
+        _ => ron::from_str(line),
+    }
+}
+
+/// version 1 had a single `Exhausted` status for both caught up and completed
+mod v1 {
+    use crate::{SiteData, VideoWork, WatchPosition};
+
+    #[derive(serde::Deserialize)]
+    pub(super) struct VideoItem {
+        #[serde(default)]
+        id: u32,
+        work: VideoWork,
+        site_data: SiteData,
+        watch_data: WatchData,
+        ongoing: bool,
+        updated: chrono::NaiveDate,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct WatchData {
+        status: WatchStatus,
+        position: Option<WatchPosition>,
+    }
+
+    #[derive(serde::Deserialize)]
+    enum WatchStatus {
+        Virgin,
+        Partial,
+        Exhausted,
+    }
+
+    impl From<VideoItem> for crate::VideoItem {
+        fn from(item: VideoItem) -> Self {
+            let status = match item.watch_data.status {
+                WatchStatus::Virgin => crate::WatchStatus::Virgin,
+                WatchStatus::Partial => crate::WatchStatus::Partial,
+                // reconciled below
+                WatchStatus::Exhausted => crate::WatchStatus::Completed,
+            };
+            let mut video_item = Self {
+                id: item.id,
+                work: item.work,
+                site_data: item.site_data,
+                watch_data: crate::WatchData { status, position: item.watch_data.position },
+                opinion: crate::Opinion::default(),
+                tags: Default::default(),
+                priority: None,
+                ongoing: item.ongoing,
+                updated: item.updated,
+                history: Vec::new(),
+            };
+            video_item.reconcile_status();
+            video_item
+        }
+    }
+}
@@ -1,53 +1,1404 @@
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+mod check;
+mod format;
+mod search;
+mod stats;
+
+pub use check::{Problem, ProblemKind};
+pub use format::CURRENT_VERSION;
+pub use stats::{Count, Stats, Untouched};
+
+use std::{cmp::Ordering, collections::{BTreeMap, BTreeSet}, fmt, fs::{self, File, OpenOptions, TryLockError}, io::{self, BufRead as _, BufReader, BufWriter, Read as _, Seek as _, SeekFrom, Write}, path::{Path, PathBuf}, str::FromStr};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct VideoWork {
     pub title: String,
     pub year: u16,
     pub medium: VideoWorkMedium,
+    /// the number of episodes in each season, first season first, as far as known; empty if unknown
+    #[serde(default)]
+    pub episodes: Vec<u16>,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize)]
 pub enum VideoWorkMedium {
     Movie,
     TvShow,
     Anime,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct VideoItem {
+    /// unique within a watchlist and never changed once assigned; 0 until assigned
+    #[serde(default)]
+    pub id: u32,
     pub work: VideoWork,
 
     // changing / opinionated properties
     pub site_data: SiteData,
     pub watch_data: WatchData,
+    #[serde(default)]
+    pub opinion: Opinion,
+    /// free-form groupings such as `comfort`; see `VideoItem::parse_tag`
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    /// place in the queue of what to watch next, 1 first; unqueued items come after all queued ones
+    #[serde(default)]
+    pub priority: Option<u16>,
     pub ongoing: bool,
     pub updated: chrono::NaiveDate,
+    /// what happened to the item and when, oldest first; only ever appended to
+    #[serde(default)]
+    pub history: Vec<HistoryEvent>,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct SiteData {
     pub tracker: Option<String>,
     pub watch: Option<String>,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct WatchData {
     pub status: WatchStatus,
     pub position: Option<WatchPosition>,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
 pub enum WatchStatus {
     Virgin,
+    /// not started, but meant to be
+    PlanToWatch,
     Partial,
-    /// this wording is bad; it means finished for single part media (movies) and
-    /// "seen all that has been released" for multi part media, whether it is ongoing or not
-    Exhausted,
+    /// watching again after having seen it all; which rewatch this is, counting from 1
+    Rewatching(u16),
+    /// started, and paused for now
+    OnHold,
+    /// started, and given up on
+    Dropped,
+    /// seen everything released so far of an ongoing series
+    CaughtUp,
+    /// seen all of a movie, or of a series that has finished
+    Completed,
+}
+
+/// ratings are out of 10 whatever `RatingScale` they are entered and shown in
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Opinion {
+    pub rating: Option<u8>,
+    /// by season number
+    pub season_ratings: BTreeMap<u16, u8>,
+    pub review: Option<String>,
+}
+
+/// how ratings are entered and shown
+#[derive(Debug, Clone, Copy, Default)]
+pub enum RatingScale {
+    /// 1 to 10
+    #[default]
+    Ten,
+    /// 0.5 to 5 in half stars
+    FiveStars,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HistoryEvent {
+    pub date: chrono::NaiveDate,
+    pub kind: HistoryEventKind,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum HistoryEventKind {
+    /// added to the watchlist
+    Added,
+    Started,
+    /// watched up to and including the position
+    Watched(WatchPosition),
+    StatusChanged { from: WatchStatus, to: WatchStatus },
+    Completed,
 }
 
 /// the last position that has been watched i.e. should watch the episode after the position
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct WatchPosition {
     pub season: u16,
     /// if none, means "watched the season"
     pub episode: Option<u16>,
 }
+
+impl VideoItem {
+    /// records `episodes` more watched episodes as of `today`; movies have no episodes, and a
+    /// finished series watched through its last episode has none left, so both are left untouched,
+    /// in which case this returns false
+    pub fn advance(&mut self, episodes: u16, today: chrono::NaiveDate) -> bool {
+        if let VideoWorkMedium::Movie = self.work.medium {
+            return false;
+        }
+        let before = self.watch_data.clone();
+        let position = match &self.watch_data.position {
+            Some(position) => position.advanced(episodes),
+            None => WatchPosition { season: 1, episode: Some(episodes) },
+        };
+        let mut position = position.carried_over(&self.work.episodes);
+        // a finished series has no episodes beyond the known ones
+        if !self.ongoing && let Some(last) = self.work.last_episode() && position > last {
+            position = last;
+        }
+        if self.watch_data.position.as_ref() == Some(&position) {
+            return false;
+        }
+        self.watch_data.position = Some(position);
+        // watching the next episode is (re)starting a show that was not being watched
+        if let WatchStatus::Virgin | WatchStatus::PlanToWatch | WatchStatus::OnHold | WatchStatus::Dropped = self.watch_data.status {
+            self.watch_data.status = WatchStatus::Partial;
+        }
+        self.catch_up();
+        self.record_changes(&before, today);
+        self.updated = today;
+        true
+    }
+
+    /// adds history events for how the watch data differs from what it was `before`, as of `today`
+    pub fn record_changes(&mut self, before: &WatchData, today: chrono::NaiveDate) {
+        let mut record = |kind| self.history.push(HistoryEvent { date: today, kind });
+        let (from, to) = (&before.status, &self.watch_data.status);
+        let started = !from.started() && to.started() && *to != WatchStatus::Completed;
+        if started {
+            record(HistoryEventKind::Started);
+        }
+        if let Some(position) = &self.watch_data.position
+            && before.position.as_ref().is_none_or(|before| position > before)
+        {
+            record(HistoryEventKind::Watched(position.clone()));
+        }
+        match to {
+            WatchStatus::Completed if from != to => record(HistoryEventKind::Completed),
+            _ if from != to && !started => record(HistoryEventKind::StatusChanged { from: from.clone(), to: to.clone() }),
+            _ => (),
+        }
+    }
+
+    /// the episodes watched and the known total, e.g. `(23, 48)`; `None` for movies, and for series
+    /// whose episode counts are unknown or do not reach as far as the position
+    pub fn progress(&self) -> Option<(u32, u32)> {
+        if let VideoWorkMedium::Movie = self.work.medium {
+            return None;
+        }
+        let total = self.work.total_episodes().filter(|&total| total > 0)?;
+        let watched = match &self.watch_data.position {
+            Some(position) => position.episode_number(&self.work.episodes)?,
+            None => 0,
+        };
+        Some((watched.min(total), total))
+    }
+
+    /// `progress` as a percentage, rounded to the nearest
+    pub fn percent_watched(&self) -> Option<u32> {
+        self.progress().map(|(watched, total)| (watched * 200 + total) / (total * 2))
+    }
+
+    /// a tag as stored: trimmed and lowercased, with spaces as dashes; commas are not allowed
+    pub fn parse_tag(s: &str) -> Result<String, ParseValueError> {
+        let tag = s.trim().to_lowercase().split_whitespace().collect::<Vec<_>>().join("-");
+        match tag.is_empty() || tag.contains(',') {
+            true => Err(ParseValueError(format!("invalid tag \"{s}\" (expected e.g. with-partner)"))),
+            false => Ok(tag),
+        }
+    }
+
+    /// the episode to watch next, i.e. the one after the position; `None` for movies and once the
+    /// last known episode has been watched
+    pub fn next_episode(&self) -> Option<WatchPosition> {
+        if let VideoWorkMedium::Movie = self.work.medium {
+            return None;
+        }
+        if self.remaining() == Some(0) {
+            return None;
+        }
+        Some(match &self.watch_data.position {
+            Some(position) => position.advanced(1).carried_over(&self.work.episodes),
+            None => WatchPosition { season: 1, episode: Some(1) },
+        })
+    }
+
+    /// queued items first, by priority, then the most recently updated
+    pub fn queue_order(&self, other: &Self) -> Ordering {
+        self.priority.is_none().cmp(&other.priority.is_none())
+            .then_with(|| self.priority.cmp(&other.priority))
+            .then_with(|| other.updated.cmp(&self.updated))
+    }
+
+    /// the known episodes not watched yet; see `progress`
+    pub fn remaining(&self) -> Option<u32> {
+        self.progress().map(|(watched, total)| total - watched)
+    }
+
+    /// marks a series being watched as caught up (or completed, see `reconcile_status`) once its
+    /// position reaches the last known episode
+    pub fn catch_up(&mut self) {
+        if let WatchStatus::Partial | WatchStatus::Rewatching(_) = self.watch_data.status
+            && self.remaining() == Some(0)
+        {
+            self.watch_data.status = WatchStatus::CaughtUp;
+            self.reconcile_status();
+        }
+    }
+
+    /// makes a caught up or completed status agree with `ongoing`: only an ongoing series can be
+    /// caught up, and it cannot be completed yet
+    pub fn reconcile_status(&mut self) {
+        let ongoing_series = self.ongoing && !matches!(self.work.medium, VideoWorkMedium::Movie);
+        match (&self.watch_data.status, ongoing_series) {
+            (WatchStatus::CaughtUp, false) => self.watch_data.status = WatchStatus::Completed,
+            (WatchStatus::Completed, true) => self.watch_data.status = WatchStatus::CaughtUp,
+            _ => (),
+        }
+    }
+}
+
+impl VideoWork {
+    /// the title without a leading article, lowercased, for sorting
+    pub fn sort_title(&self) -> String {
+        let lower = self.title.trim().to_lowercase();
+        ["the ", "a ", "an "].iter()
+            .find_map(|article| lower.strip_prefix(article))
+            .unwrap_or(&lower)
+            .trim_start()
+            .to_string()
+    }
+
+    /// all known episodes; `None` if no episode counts are known
+    pub fn total_episodes(&self) -> Option<u32> {
+        (!self.episodes.is_empty()).then(|| self.episodes.iter().copied().map(u32::from).sum())
+    }
+
+    /// the position of the last known episode; `None` if no episode counts are known
+    pub fn last_episode(&self) -> Option<WatchPosition> {
+        let season = u16::try_from(self.episodes.len()).ok().filter(|&season| season > 0)?;
+        Some(WatchPosition { season, episode: self.episodes.last().copied() })
+    }
+
+    /// episode counts per season separated by commas or spaces, e.g. `12,12,24`; empty for none
+    pub fn parse_episodes(s: &str) -> Result<Vec<u16>, ParseValueError> {
+        s.split([',', ' '])
+            .filter(|count| !count.is_empty())
+            .map(|count| count.parse::<u16>()
+                .map_err(|_| ParseValueError(format!("invalid episode count \"{count}\" (expected e.g. 12,12,24)"))))
+            .collect()
+    }
+}
+
+impl WatchPosition {
+    /// the position `episodes` episodes later; after a watched season, the next season starts at episode 1
+    pub fn advanced(&self, episodes: u16) -> Self {
+        match self.episode {
+            Some(episode) => Self { season: self.season, episode: Some(episode.saturating_add(episodes)) },
+            None => Self { season: self.season.saturating_add(1), episode: Some(episodes) },
+        }
+    }
+
+    /// the same position with episodes past the end of a season counted into the next one, as far as
+    /// the `episodes` in each season are known
+    pub fn carried_over(&self, episodes: &[u16]) -> Self {
+        let mut position = self.clone();
+        while let Some(episode) = position.episode
+            && let Some(&count) = usize::from(position.season).checked_sub(1).and_then(|i| episodes.get(i))
+            && episode > count
+            && usize::from(position.season) < episodes.len()
+        {
+            position = Self { season: position.season + 1, episode: Some(episode - count) };
+        }
+        position
+    }
+
+    /// how many episodes have been watched up to and including this position, given the `episodes`
+    /// in each season; `None` if the season is not among them
+    pub fn episode_number(&self, episodes: &[u16]) -> Option<u32> {
+        let season = usize::from(self.season).checked_sub(1)?;
+        let count = *episodes.get(season)?;
+        let before: u32 = episodes[..season].iter().copied().map(u32::from).sum();
+        Some(before + u32::from(self.episode.unwrap_or(count).min(count)))
+    }
+}
+
+/// a watched season (no episode) comes after every episode of that season
+impl Ord for WatchPosition {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.season.cmp(&other.season).then_with(|| match (self.episode, other.episode) {
+            (Some(episode), Some(other)) => episode.cmp(&other),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        })
+    }
+}
+
+impl PartialOrd for WatchPosition {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for VideoWorkMedium {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Movie => "movie",
+            Self::TvShow => "tvshow",
+            Self::Anime => "anime",
+        })
+    }
+}
+
+/// `rewatching` for a first rewatch, `rewatching:N` for later ones
+impl fmt::Display for WatchStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Virgin => "virgin",
+            Self::PlanToWatch => "plan-to-watch",
+            Self::Partial => "partial",
+            Self::Rewatching(1) => "rewatching",
+            Self::Rewatching(rewatch) => return f.pad(&format!("rewatching:{rewatch}")),
+            Self::OnHold => "on-hold",
+            Self::Dropped => "dropped",
+            Self::CaughtUp => "caught-up",
+            Self::Completed => "completed",
+        })
+    }
+}
+
+impl fmt::Display for HistoryEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}  {}", self.date, self.kind)
+    }
+}
+
+impl fmt::Display for HistoryEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added => f.write_str("added"),
+            Self::Started => f.write_str("started"),
+            Self::Watched(position) => write!(f, "watched through {position}"),
+            Self::StatusChanged { from, to } => write!(f, "{from} -> {to}"),
+            Self::Completed => f.write_str("completed"),
+        }
+    }
+}
+
+impl RatingScale {
+    /// a rating out of 10 from one on this scale, e.g. `8`, or `4.5` for five stars
+    pub fn parse_rating(&self, s: &str) -> Result<u8, ParseValueError> {
+        let invalid = || match self {
+            Self::Ten => ParseValueError(format!("invalid rating \"{s}\" (expected a whole number from 1 to 10)")),
+            Self::FiveStars => ParseValueError(format!("invalid rating \"{s}\" (expected 0.5 to 5 in steps of 0.5)")),
+        };
+        let rating = match self {
+            Self::Ten => s.trim().parse::<u8>().map_err(|_| invalid())?,
+            Self::FiveStars => {
+                let stars = s.trim().parse::<f32>().map_err(|_| invalid())? * 2.0;
+                match stars.fract() == 0.0 && (0.0..=10.0).contains(&stars) {
+                    true => stars as u8,
+                    false => return Err(invalid()),
+                }
+            },
+        };
+        match (1..=10).contains(&rating) {
+            true => Ok(rating),
+            false => Err(invalid()),
+        }
+    }
+
+    /// a rating out of 10 as a number on this scale
+    pub fn value(&self, rating: u8) -> f32 {
+        match self {
+            Self::Ten => f32::from(rating),
+            Self::FiveStars => f32::from(rating) / 2.0,
+        }
+    }
+
+    /// a rating out of 10 on this scale, e.g. `8/10` or `★★★★☆`
+    pub fn format_rating(&self, rating: u8) -> String {
+        match self {
+            Self::Ten => format!("{rating}/10"),
+            Self::FiveStars => {
+                let rating = usize::from(rating.min(10));
+                let half = "½".repeat(rating % 2);
+                format!("{}{half}{}", "★".repeat(rating / 2), "☆".repeat(5 - rating / 2 - rating % 2))
+            },
+        }
+    }
+}
+
+/// `10` (or `ten`) for the 10-point scale, `5` (or `stars`) for five stars
+impl FromStr for RatingScale {
+    type Err = ParseValueError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "10" | "ten" => Ok(Self::Ten),
+            "5" | "stars" | "five-stars" => Ok(Self::FiveStars),
+            _ => Err(ParseValueError(format!("unknown rating scale \"{s}\" (expected 10 or stars)"))),
+        }
+    }
+}
+
+/// `S02E05`, or `S02` for a whole season
+impl fmt::Display for WatchPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let position = match self.episode {
+            Some(episode) => format!("S{:02}E{:02}", self.season, episode),
+            None => format!("S{:02}", self.season),
+        };
+        f.pad(&position)
+    }
+}
+
+impl FromStr for VideoWorkMedium {
+    type Err = ParseValueError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "movie" => Ok(Self::Movie),
+            "tvshow" => Ok(Self::TvShow),
+            "anime" => Ok(Self::Anime),
+            _ => Err(ParseValueError(format!("unknown medium \"{s}\" (expected movie, tvshow or anime)"))),
+        }
+    }
+}
+
+/// also accepts the MyAnimeList and AniList names, see `WatchStatus::mal` and `WatchStatus::anilist`
+impl FromStr for WatchStatus {
+    type Err = ParseValueError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.trim().to_lowercase().replace('_', "-");
+        if let Some(rewatch) = lower.strip_prefix("rewatching:") {
+            return match rewatch.parse::<u16>() {
+                Ok(rewatch) if rewatch > 0 => Ok(Self::Rewatching(rewatch)),
+                _ => Err(ParseValueError(format!("invalid rewatch \"{rewatch}\" (expected a number from 1)"))),
+            };
+        }
+        match lower.as_str() {
+            "virgin" => Ok(Self::Virgin),
+            "plan-to-watch" | "planning" => Ok(Self::PlanToWatch),
+            "partial" | "watching" | "current" => Ok(Self::Partial),
+            "rewatching" | "repeating" => Ok(Self::Rewatching(1)),
+            "on-hold" | "paused" => Ok(Self::OnHold),
+            "dropped" => Ok(Self::Dropped),
+            "caught-up" | "caughtup" => Ok(Self::CaughtUp),
+            // the old name for both; `VideoItem::reconcile_status` tells them apart
+            "completed" | "exhausted" => Ok(Self::Completed),
+            _ => Err(ParseValueError(format!(
+                "unknown status \"{s}\" (expected virgin, plan-to-watch, partial, rewatching[:N], on-hold, dropped, caught-up or completed)"
+            ))),
+        }
+    }
+}
+
+impl WatchStatus {
+    /// whether both are the same status, disregarding which rewatch it is
+    pub fn same_kind(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    /// whether watching has started, so that there is a position to speak of
+    pub fn started(&self) -> bool {
+        !matches!(self, Self::Virgin | Self::PlanToWatch)
+    }
+
+    /// the MyAnimeList list status; MyAnimeList marks a rewatch with a separate `is_rewatching`
+    /// flag on a completed entry, and has no notion of being caught up
+    pub fn mal(&self) -> &'static str {
+        match self {
+            Self::Virgin | Self::PlanToWatch => "plan_to_watch",
+            Self::Partial | Self::CaughtUp => "watching",
+            Self::Rewatching(_) | Self::Completed => "completed",
+            Self::OnHold => "on_hold",
+            Self::Dropped => "dropped",
+        }
+    }
+
+    /// the AniList `MediaListStatus`; AniList counts finished rewatches in `repeat`, which is one
+    /// less than the rewatch in progress
+    pub fn anilist(&self) -> &'static str {
+        match self {
+            Self::Virgin | Self::PlanToWatch => "PLANNING",
+            Self::Partial | Self::CaughtUp => "CURRENT",
+            Self::Rewatching(_) => "REPEATING",
+            Self::OnHold => "PAUSED",
+            Self::Dropped => "DROPPED",
+            Self::Completed => "COMPLETED",
+        }
+    }
+}
+
+/// accepts `S2E5` or `2:5`, and `S2` or `2` for a whole season
+impl FromStr for WatchPosition {
+    type Err = ParseValueError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseValueError(format!("invalid position \"{s}\" (expected e.g. S2E5, S2, 2:5 or 2)"));
+        let lower = s.trim().to_lowercase();
+        let (season, episode) = match lower.strip_prefix('s') {
+            Some(rest) => match rest.split_once('e') {
+                Some((season, episode)) => (season, Some(episode)),
+                None => (rest, None),
+            },
+            None => match lower.split_once(':') {
+                Some((season, episode)) => (season, Some(episode)),
+                None => (lower.as_str(), None),
+            },
+        };
+        let season = season.parse::<u16>().map_err(|_| invalid())?;
+        let episode = match episode {
+            Some(episode) => Some(episode.parse::<u16>().map_err(|_| invalid())?),
+            None => None,
+        };
+        Ok(Self { season, episode })
+    }
+}
+
+/// a `field=value` assignment to a single field of a `VideoItem`
+///
+/// fields are `title`, `year`, `medium`, `episodes`, `tracker`, `watch`, `status`, `position`,
+/// `rating`, `season-rating` (as `season:rating`), `review`, `priority`, `ongoing` and `updated`;
+/// an empty value (or `none` for `position`) clears an optional field
+#[derive(Debug, Clone)]
+pub enum FieldAssignment {
+    Title(String),
+    Year(u16),
+    Medium(VideoWorkMedium),
+    Episodes(Vec<u16>),
+    Tracker(Option<String>),
+    Watch(Option<String>),
+    Status(WatchStatus),
+    Position(Option<WatchPosition>),
+    /// out of 10
+    Rating(Option<u8>),
+    SeasonRating(u16, Option<u8>),
+    Review(Option<String>),
+    Priority(Option<u16>),
+    Ongoing(bool),
+    Updated(chrono::NaiveDate),
+}
+
+impl FieldAssignment {
+    pub fn apply(&self, video_item: &mut VideoItem) {
+        match self.clone() {
+            Self::Title(title) => video_item.work.title = title,
+            Self::Year(year) => video_item.work.year = year,
+            Self::Medium(medium) => video_item.work.medium = medium,
+            Self::Episodes(episodes) => video_item.work.episodes = episodes,
+            Self::Tracker(tracker) => video_item.site_data.tracker = tracker,
+            Self::Watch(watch) => video_item.site_data.watch = watch,
+            Self::Status(status) => video_item.watch_data.status = status,
+            Self::Position(position) => video_item.watch_data.position = position,
+            Self::Rating(rating) => video_item.opinion.rating = rating,
+            Self::SeasonRating(season, Some(rating)) => {
+                video_item.opinion.season_ratings.insert(season, rating);
+            },
+            Self::SeasonRating(season, None) => {
+                video_item.opinion.season_ratings.remove(&season);
+            },
+            Self::Review(review) => video_item.opinion.review = review,
+            Self::Priority(priority) => video_item.priority = priority,
+            Self::Ongoing(ongoing) => video_item.ongoing = ongoing,
+            Self::Updated(updated) => video_item.updated = updated,
+        }
+    }
+
+    /// parses a `field=value` assignment; ratings are on `scale`
+    pub fn parse(s: &str, scale: RatingScale) -> Result<Self, ParseValueError> {
+        let Some((field, value)) = s.split_once('=') else {
+            return Err(ParseValueError(format!("expected field=value but found \"{s}\"")));
+        };
+        let value = value.trim();
+        let optional = |value: &str| (!value.is_empty()).then(|| value.to_string());
+        match field.trim() {
+            "title" => match value.is_empty() {
+                true => Err(ParseValueError("title cannot be empty".to_string())),
+                false => Ok(Self::Title(value.to_string())),
+            },
+            "year" => value.parse::<u16>()
+                .map(Self::Year)
+                .map_err(|_| ParseValueError(format!("invalid year \"{value}\""))),
+            "medium" => value.parse().map(Self::Medium),
+            "episodes" => VideoWork::parse_episodes(value).map(Self::Episodes),
+            "tracker" => Ok(Self::Tracker(optional(value))),
+            "watch" => Ok(Self::Watch(optional(value))),
+            "status" => value.parse().map(Self::Status),
+            "position" => match value {
+                "" | "none" => Ok(Self::Position(None)),
+                _ => value.parse().map(|position| Self::Position(Some(position))),
+            },
+            "rating" => match value {
+                "" => Ok(Self::Rating(None)),
+                _ => scale.parse_rating(value).map(|rating| Self::Rating(Some(rating))),
+            },
+            "season-rating" => {
+                let Some((season, rating)) = value.split_once(':') else {
+                    return Err(ParseValueError(format!("invalid season rating \"{value}\" (expected season:rating, e.g. 2:8)")));
+                };
+                let season = season.trim().parse::<u16>()
+                    .map_err(|_| ParseValueError(format!("invalid season \"{season}\"")))?;
+                match rating.trim() {
+                    "" => Ok(Self::SeasonRating(season, None)),
+                    rating => scale.parse_rating(rating).map(|rating| Self::SeasonRating(season, Some(rating))),
+                }
+            },
+            "review" => Ok(Self::Review(optional(value))),
+            "priority" => match value {
+                "" => Ok(Self::Priority(None)),
+                _ => value.parse::<u16>()
+                    .map(|priority| Self::Priority(Some(priority)))
+                    .map_err(|_| ParseValueError(format!("invalid priority \"{value}\" (expected a number, 1 first)"))),
+            },
+            "ongoing" => value.parse::<bool>()
+                .map(Self::Ongoing)
+                .map_err(|_| ParseValueError(format!("invalid ongoing \"{value}\" (expected true or false)"))),
+            "updated" => value.parse::<chrono::NaiveDate>()
+                .map(Self::Updated)
+                .map_err(|_| ParseValueError(format!("invalid updated date \"{value}\" (expected yyyy-mm-dd)"))),
+            field => Err(ParseValueError(format!(
+                "unknown field \"{field}\" (expected title, year, medium, episodes, tracker, watch, status, position, rating, season-rating, review, priority, ongoing or updated)"
+            ))),
+        }
+    }
+}
+
+/// a command line or prompt value that does not describe a valid field value
+#[derive(Debug)]
+pub struct ParseValueError(pub String);
+
+impl fmt::Display for ParseValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ParseValueError {}
+
+/// a watchlist data file; one RON serialized `VideoItem` per line
+///
+/// lines that fail to parse, such as broken hand edits, are carried through rewrites unchanged;
+/// a file in a newer format is refused rather than rewritten; rewrites (`append`, `update`,
+/// `remove`) go through the temp file, which is synced to disk and then renamed over the data
+/// file, so a crash leaves either the old or the new list but never neither; the rename is only
+/// atomic if the temp file is in the same directory (on the same file system)
+#[derive(Debug)]
+pub struct Watchlist {
+    datafile: PathBuf,
+    tempfile: PathBuf,
+}
+
+impl Watchlist {
+    /// fails if the data file does not exist, cannot be read, or is in a newer format
+    pub fn open(datafile: impl Into<PathBuf>) -> Result<Self, Error> {
+        let watchlist = Self::new(datafile);
+        VideoItems::open(&watchlist.datafile)?;
+        Ok(watchlist)
+    }
+
+    /// like `open`, but the data file does not have to exist yet (or any more, see `recover_tempfile`);
+    /// the temp file defaults to `<stem>.temp.ron` next to the data file
+    pub fn new(datafile: impl Into<PathBuf>) -> Self {
+        let datafile: PathBuf = datafile.into();
+        let stem = datafile.file_stem().unwrap_or_default().to_string_lossy();
+        let tempfile = datafile.with_file_name(format!("{stem}.temp.ron"));
+        Self { datafile, tempfile }
+    }
+
+    pub fn with_tempfile(self, tempfile: impl Into<PathBuf>) -> Self {
+        Self { tempfile: tempfile.into(), ..self }
+    }
+
+    pub fn datafile(&self) -> &Path {
+        &self.datafile
+    }
+
+    pub fn tempfile(&self) -> &Path {
+        &self.tempfile
+    }
+
+    /// `<stem>.lock` next to the data file; unlike the data file it is never replaced,
+    /// so a lock on it stays meaningful across rewrites
+    pub fn lockfile(&self) -> PathBuf {
+        let stem = self.datafile.file_stem().unwrap_or_default().to_string_lossy();
+        self.datafile.with_file_name(format!("{stem}.lock"))
+    }
+
+    /// takes an advisory lock on the watchlist, held until the returned `Lock` is dropped;
+    /// without `wait`, fails with `Error::Locked` instead of blocking while another process holds it
+    pub fn lock(&self, mode: LockMode, wait: bool) -> Result<Lock, Error> {
+        let lockfile = self.lockfile();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&lockfile)?;
+        let result = match (&mode, wait) {
+            (LockMode::Shared, true) => file.lock_shared().map_err(TryLockError::Error),
+            (LockMode::Exclusive, true) => file.lock().map_err(TryLockError::Error),
+            (LockMode::Shared, false) => file.try_lock_shared(),
+            (LockMode::Exclusive, false) => file.try_lock(),
+        };
+        match result {
+            Err(TryLockError::WouldBlock) => {
+                let pid = fs::read_to_string(&lockfile).ok().and_then(|pid| pid.trim().parse().ok());
+                return Err(Error::Locked { pid });
+            },
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+            Ok(()) => (),
+        }
+        // only the exclusive holder can say who it is without racing other holders
+        let mut crashed_holder = None;
+        if let LockMode::Exclusive = mode {
+            // a clean release empties the file, so a pid left in it belongs to a process that died holding the lock
+            let mut previous = String::new();
+            file.read_to_string(&mut previous)?;
+            crashed_holder = previous.trim().parse().ok();
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            write!(file, "{}", std::process::id())?;
+            file.flush()?;
+        }
+        Ok(Lock { file, mode, crashed_holder })
+    }
+
+    pub fn iter(&self) -> Result<VideoItems, Error> {
+        VideoItems::open(&self.datafile)
+    }
+
+    /// gives `video_item` the next free id and adds it at the end; a rewrite, as the ids line has
+    /// to count the new id
+    pub fn append(&self, video_item: &mut VideoItem) -> Result<(), Error> {
+        video_item.id = self.next_id()?;
+        self.rewrite(video_item.id + 1, Some(video_item), |video_item, writer| write_video_item(writer, &video_item))
+    }
+
+    /// the format version of the data file; see `format::CURRENT_VERSION`
+    pub fn format_version(&self) -> Result<u32, Error> {
+        Ok(self.iter()?.version())
+    }
+
+    /// whether the data file is in an older format, or lacks the header line naming its format;
+    /// an empty file has nothing to migrate
+    pub fn needs_migration(&self) -> Result<bool, Error> {
+        let items = self.iter()?;
+        Ok(match items.has_header {
+            true => items.version < CURRENT_VERSION,
+            false => items.first_item.is_some(),
+        })
+    }
+
+    /// upgrades the data file to the current format after copying it to `<datafile>.v<version>.bak`;
+    /// returns the backup's path, or `None` if the file was already current
+    ///
+    /// any other rewrite of an older file upgrades it as well, and takes the same backup first
+    pub fn migrate(&self) -> Result<Option<PathBuf>, Error> {
+        let Some(backup) = self.back_up_outdated()? else {
+            return Ok(None);
+        };
+        // every rewrite writes the current format
+        self.rewrite_backed_up(self.next_id()?, None, |video_item, writer| write_video_item(writer, &video_item))?;
+        Ok(Some(backup))
+    }
+
+    /// copies the data file to `<datafile>.v<version>.bak` if it needs migration
+    fn back_up_outdated(&self) -> Result<Option<PathBuf>, Error> {
+        if !self.needs_migration()? {
+            return Ok(None);
+        }
+        let mut backup = self.datafile.clone().into_os_string();
+        backup.push(format!(".v{}.bak", self.format_version()?));
+        let backup = PathBuf::from(backup);
+        fs::copy(&self.datafile, &backup)?;
+        Ok(Some(backup))
+    }
+
+    /// calls `edit` on every item and writes the result back;
+    /// the data file is left untouched if any item fails to edit or write
+    pub fn update(&self, mut edit: impl FnMut(&mut VideoItem) -> Result<(), Error>) -> Result<(), Error> {
+        self.rewrite(self.next_id()?, None, |mut video_item, writer| {
+            edit(&mut video_item)?;
+            write_video_item(writer, &video_item)
+        })
+    }
+
+    /// removes every item matching `predicate` and returns the removed items;
+    /// the data file is left untouched if any item fails to write
+    pub fn remove(&self, mut predicate: impl FnMut(&VideoItem) -> bool) -> Result<Vec<VideoItem>, Error> {
+        let mut removed = Vec::new();
+        // the removed ids stay given out
+        self.rewrite(self.next_id()?, None, |video_item, writer| {
+            match predicate(&video_item) {
+                true => removed.push(video_item),
+                false => write_video_item(writer, &video_item)?,
+            }
+            Ok(())
+        })?;
+        Ok(removed)
+    }
+
+    /// whether any item was written before ids existed
+    pub fn has_missing_ids(&self) -> Result<bool, Error> {
+        Ok(self.iter()?.any(|video_item| video_item.is_ok_and(|vi| vi.id == 0)))
+    }
+
+    /// gives every item without an id (written before ids existed) a fresh one;
+    /// the data file is only rewritten if there is such an item
+    pub fn assign_missing_ids(&self) -> Result<(), Error> {
+        let missing = self.iter()?.filter(|video_item| video_item.as_ref().is_ok_and(|vi| vi.id == 0)).count() as u32;
+        if missing == 0 {
+            return Ok(());
+        }
+        let mut next_id = self.next_id()?;
+        self.rewrite(next_id + missing, None, |mut video_item, writer| {
+            if video_item.id == 0 {
+                video_item.id = next_id;
+                next_id += 1;
+            }
+            write_video_item(writer, &video_item)
+        })
+    }
+
+    /// what a `<title or #id>` argument refers to: `#12` is the item with id 12; otherwise items
+    /// whose title matches exactly or, unless `exact`, loosely (see `Selector::Fuzzy`)
+    ///
+    /// unless `exact`, a bare number is an id too if there is an item with it, and fails with
+    /// `Error::Ambiguous` if a title matches it as well
+    pub fn select(&self, key: &str, exact: bool) -> Result<Selector, Error> {
+        if let Some(Ok(id)) = key.trim().strip_prefix('#').map(str::parse::<u32>) {
+            return Ok(Selector::Id(id));
+        }
+        let title = match exact {
+            true => Selector::Title(key.to_string()),
+            false => Selector::Fuzzy(search::normalize(key)),
+        };
+        if !exact && let Ok(id) = key.trim().parse::<u32>() {
+            let (mut is_id, mut is_title) = (false, false);
+            for video_item in self.iter()?.filter_map(Result::ok) {
+                is_id |= video_item.id == id;
+                is_title |= title.rank(&video_item) == Some(0);
+            }
+            match (is_id, is_title) {
+                (true, true) => return Err(Error::Ambiguous(id)),
+                (true, false) => return Ok(Selector::Id(id)),
+                (false, _) => (),
+            }
+        }
+        Ok(title)
+    }
+
+    /// the id the next item gets: past every id given out so far, as far as the ids line knows, and
+    /// past every id in use; unparseable lines are not considered
+    fn next_id(&self) -> Result<u32, Error> {
+        let video_items = self.iter()?;
+        let given_out = video_items.next_id.unwrap_or(0);
+        let max = video_items
+            .filter_map(Result::ok)
+            .map(|video_item| video_item.id)
+            .max()
+            .unwrap_or(0);
+        Ok(given_out.max(max + 1))
+    }
+
+    /// compares a temp file left behind by an interrupted rewrite with the data file;
+    /// only meaningful while holding the exclusive lock, as otherwise the temp file may belong to a
+    /// rewrite in progress
+    pub fn inspect_tempfile(&self) -> Result<TempfileReport, Error> {
+        let datafile = FileSummary::read(&self.datafile)?;
+        let tempfile = FileSummary::read(&self.tempfile)?;
+        let identical = datafile.is_some() && tempfile.is_some() && fs::read(&self.datafile)? == fs::read(&self.tempfile)?;
+        Ok(TempfileReport { datafile, tempfile, identical })
+    }
+
+    /// deletes a temp file left behind by an interrupted rewrite, keeping the data file as it is
+    pub fn discard_tempfile(&self) -> Result<(), Error> {
+        fs::remove_file(&self.tempfile)?;
+        Ok(())
+    }
+
+    /// replaces the data file with a temp file left behind by an interrupted rewrite
+    pub fn recover_tempfile(&self) -> Result<(), Error> {
+        File::open(&self.tempfile)?.sync_all()?;
+        fs::rename(&self.tempfile, &self.datafile)?;
+        self.sync_directory()?;
+        Ok(())
+    }
+
+    /// writes every item through `f`, then `appended` if given, under an ids line with `next_id`;
+    /// an older file is backed up first, as the rewrite upgrades it
+    fn rewrite(
+        &self,
+        next_id: u32,
+        appended: Option<&VideoItem>,
+        f: impl FnMut(VideoItem, &mut BufWriter<File>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.back_up_outdated()?;
+        self.rewrite_backed_up(next_id, appended, f)
+    }
+
+    fn rewrite_backed_up(
+        &self,
+        next_id: u32,
+        appended: Option<&VideoItem>,
+        f: impl FnMut(VideoItem, &mut BufWriter<File>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let tempfile = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(&self.tempfile)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::AlreadyExists => Error::StaleTempfile(self.tempfile.clone()),
+                _ => e.into(),
+            })?;
+        if let Err(e) = self.write_to_temp_file(tempfile, next_id, appended, f) {
+            // nothing has touched the data file yet, so the partial temp file can go
+            let _ = fs::remove_file(&self.tempfile);
+            return Err(e);
+        }
+        fs::rename(&self.tempfile, &self.datafile)?;
+        self.sync_directory()?;
+        Ok(())
+    }
+
+    /// makes the rename itself durable
+    #[cfg(unix)]
+    fn sync_directory(&self) -> Result<(), Error> {
+        let directory = match self.datafile.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        File::open(directory)?.sync_all()?;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn sync_directory(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn write_to_temp_file(
+        &self,
+        tempfile: File,
+        next_id: u32,
+        appended: Option<&VideoItem>,
+        mut f: impl FnMut(VideoItem, &mut BufWriter<File>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut writer = BufWriter::new(tempfile);
+        writer.write_all(format::header_line().as_bytes())?;
+        writer.write_all(format::ids_line(next_id).as_bytes())?;
+        for video_item in self.iter()? {
+            match video_item {
+                Err(Error::Parse { mut line, .. }) => {
+                    if !line.ends_with('\n') {
+                        line.push('\n');
+                    }
+                    writer.write_all(line.as_bytes())?;
+                },
+                video_item => f(video_item?, &mut writer)?,
+            }
+        }
+        if let Some(video_item) = appended {
+            write_video_item(&mut writer, video_item)?;
+        }
+        writer.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()?;
+        Ok(())
+    }
+}
+
+/// what `Watchlist::inspect_tempfile` found
+#[derive(Debug)]
+pub struct TempfileReport {
+    /// `None` if the data file is missing
+    pub datafile: Option<FileSummary>,
+    /// `None` if there is no temp file, i.e. nothing to recover
+    pub tempfile: Option<FileSummary>,
+    /// whether both files have the same content
+    pub identical: bool,
+}
+
+#[derive(Debug)]
+pub struct FileSummary {
+    pub items: usize,
+    pub unparseable_lines: usize,
+    /// a rewrite cut short usually ends in the middle of a line
+    pub ends_with_newline: bool,
+    pub modified: Option<std::time::SystemTime>,
+}
+
+impl FileSummary {
+    /// `None` if there is no such file
+    fn read(path: &Path) -> Result<Option<Self>, Error> {
+        let video_items = match VideoItems::open(path) {
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            result => result?,
+        };
+        let (mut items, mut unparseable_lines) = (0, 0);
+        for video_item in video_items {
+            match video_item {
+                Err(Error::Parse { .. }) => unparseable_lines += 1,
+                video_item => {
+                    video_item?;
+                    items += 1;
+                },
+            }
+        }
+        Ok(Some(Self {
+            items,
+            unparseable_lines,
+            ends_with_newline: fs::read(path)?.last().is_none_or(|last| *last == b'\n'),
+            modified: fs::metadata(path)?.modified().ok(),
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// for reading; any number of processes can hold it at once
+    Shared,
+    /// for writing; excludes every other lock
+    Exclusive,
+}
+
+/// an advisory lock on a watchlist, see `Watchlist::lock`; released when dropped
+#[derive(Debug)]
+pub struct Lock {
+    file: File,
+    mode: LockMode,
+    crashed_holder: Option<u32>,
+}
+
+impl Lock {
+    /// the pid of a process that died while holding the exclusive lock before this one was taken;
+    /// only known when taking the exclusive lock
+    pub fn crashed_holder(&self) -> Option<u32> {
+        self.crashed_holder
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        // the pid is only meaningful while held; closing the file releases the lock itself
+        if let LockMode::Exclusive = self.mode {
+            let _ = self.file.set_len(0);
+        }
+    }
+}
+
+/// the items a `<title or #id>` argument refers to; see `Watchlist::select`
+#[derive(Debug, Clone)]
+pub enum Selector {
+    Id(u32),
+    /// the title exactly, byte for byte
+    Title(String),
+    /// a query matching titles regardless of case and accents, as a substring, or with a
+    /// typo or two for longer queries
+    Fuzzy(String),
+}
+
+impl Selector {
+    pub fn matches(&self, video_item: &VideoItem) -> bool {
+        self.rank(video_item).is_some()
+    }
+
+    /// how well `video_item` matches, lower is better; `None` if it does not match at all
+    pub fn rank(&self, video_item: &VideoItem) -> Option<u32> {
+        match self {
+            Self::Id(id) => (video_item.id == *id).then_some(0),
+            Self::Title(title) => (video_item.work.title == *title).then_some(0),
+            Self::Fuzzy(query) => search::title_score(query, &video_item.work.title),
+        }
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "id {id}"),
+            Self::Title(title) => write!(f, "title \"{title}\""),
+            Self::Fuzzy(query) => write!(f, "a title like \"{query}\""),
+        }
+    }
+}
+
+/// the items of a data file in file order, read according to its format version;
+/// lines that fail to parse are yielded as errors
+pub struct VideoItems {
+    reader: BufReader<File>,
+    line_number: usize,
+    version: u32,
+    has_header: bool,
+    /// from the ids line, if there is one
+    next_id: Option<u32>,
+    /// the first line, if it turned out to be an item rather than a header or ids line
+    first_item: Option<String>,
+}
+
+impl VideoItems {
+    /// fails with `Error::UnsupportedVersion` for files written by a newer version
+    fn open(path: &Path) -> Result<Self, Error> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut first = String::new();
+        reader.read_line(&mut first)?;
+        let mut items = match format::parse_header(&first) {
+            Some(version) => Self { reader, line_number: 1, version, has_header: true, next_id: None, first_item: None },
+            None => Self {
+                reader,
+                line_number: 0,
+                version: format::UNVERSIONED,
+                has_header: false,
+                next_id: None,
+                first_item: (!first.is_empty()).then_some(first),
+            },
+        };
+        if items.has_header {
+            let mut second = String::new();
+            items.reader.read_line(&mut second)?;
+            match format::parse_ids(&second) {
+                Some(next_id) => {
+                    items.next_id = Some(next_id);
+                    items.line_number += 1;
+                },
+                None => items.first_item = (!second.is_empty()).then_some(second),
+            }
+        }
+        if items.version > CURRENT_VERSION {
+            return Err(Error::UnsupportedVersion(items.version));
+        }
+        Ok(items)
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// the line number, counting from 1, of the item last yielded
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+}
+
+impl Iterator for VideoItems {
+    type Item = Result<VideoItem, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = match self.first_item.take() {
+            Some(line) => line,
+            None => {
+                let mut line = String::new();
+                match self.reader.read_line(&mut line) {
+                    Err(e) => return Some(Err(e.into())),
+                    Ok(0) => return None,
+                    Ok(_) => line,
+                }
+            },
+        };
+        self.line_number += 1;
+        let line_number = self.line_number;
+        Some(format::parse_item(self.version, &line).map_err(|e| Error::Parse { line_number, line, error: Box::new(e) }))
+    }
+}
+
+fn write_video_item(writer: &mut impl Write, video_item: &VideoItem) -> Result<(), Error> {
+    let mut s = ron::to_string(video_item)?;
+    s.push('\n');
+    writer.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// an item could not be serialized
+    Ron(ron::Error),
+    /// a line of the data file could not be deserialized; `line_number` counts from 1
+    Parse { line_number: usize, line: String, error: Box<ron::error::SpannedError> },
+    /// another process holds a conflicting lock; the pid is known if it holds it exclusively
+    Locked { pid: Option<u32> },
+    /// a rewrite found the temp file already there, left behind by an interrupted rewrite
+    StaleTempfile(PathBuf),
+    /// the data file is in a format newer than `CURRENT_VERSION`
+    UnsupportedVersion(u32),
+    /// a `<title or #id>` argument is a number that is both an id and a title; see `Watchlist::select`
+    Ambiguous(u32),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::Ron(e) => write!(f, "error serializing item: {e}"),
+            Self::Parse { line_number, line, error } => {
+                write!(f, "error parsing RON on line {line_number} \"{}\": {error}", line.trim_end())
+            },
+            Self::Locked { pid: Some(pid) } => write!(f, "watchlist is locked by pid {pid}"),
+            Self::Locked { pid: None } => write!(f, "watchlist is locked by another process"),
+            Self::UnsupportedVersion(version) => write!(
+                f, "data file is in format version {version}, but only up to {CURRENT_VERSION} is supported; a newer watchlist wrote it",
+            ),
+            Self::Ambiguous(id) => write!(f, "\"{id}\" is both an id and a title"),
+            Self::StaleTempfile(path) => write!(f, "temp file {} was left behind by an interrupted rewrite", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ron::Error> for Error {
+    fn from(error: ron::Error) -> Self {
+        Self::Ron(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a directory of its own for each test, removed afterwards
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("watchlist-test-{}-{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn watchlist(&self, contents: &str) -> Watchlist {
+            let datafile = self.0.join("watchlist.ron");
+            fs::write(&datafile, contents).unwrap();
+            Watchlist::open(datafile).unwrap()
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn series(title: &str, episodes: Vec<u16>) -> VideoItem {
+        VideoItem {
+            id: 0,
+            work: VideoWork { title: title.to_string(), year: 2020, medium: VideoWorkMedium::TvShow, episodes },
+            site_data: SiteData { tracker: None, watch: None },
+            watch_data: WatchData { status: WatchStatus::Virgin, position: None },
+            opinion: Opinion::default(),
+            tags: BTreeSet::new(),
+            priority: None,
+            ongoing: false,
+            updated: chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            history: Vec::new(),
+        }
+    }
+
+    fn position(season: u16, episode: Option<u16>) -> WatchPosition {
+        WatchPosition { season, episode }
+    }
+
+    #[test]
+    fn v1_exhausted_becomes_completed_or_caught_up() {
+        let line = |ongoing: bool| format!(
+            "(id:1,work:(title:\"A\",year:2000,medium:TvShow),site_data:(tracker:None,watch:None),\
+             watch_data:(status:Exhausted,position:Some((season:2,episode:None))),ongoing:{ongoing},updated:\"2024-01-01\")",
+        );
+        let finished = format::parse_item(1, &line(false)).unwrap();
+        assert_eq!(finished.watch_data.status, WatchStatus::Completed);
+        assert_eq!(finished.watch_data.position, Some(position(2, None)));
+        let ongoing = format::parse_item(1, &line(true)).unwrap();
+        assert_eq!(ongoing.watch_data.status, WatchStatus::CaughtUp);
+    }
+
+    #[test]
+    fn unparseable_lines_survive_update_and_remove() {
+        let dir = TestDir::new("unparseable");
+        let watchlist = dir.watchlist("");
+        for title in ["A", "B", "C"] {
+            watchlist.append(&mut series(title, Vec::new())).unwrap();
+        }
+        // a broken hand edit and an item from a newer version, which has a field this one lacks
+        let broken = "(id:4,work:(title:\"half an edit\"";
+        let newer = ron::to_string(&series("D", Vec::new())).unwrap().replacen("(id:0,", "(id:5,rewatched:2,", 1);
+        let mut lines: Vec<String> = fs::read_to_string(watchlist.datafile()).unwrap().lines().map(String::from).collect();
+        lines.insert(3, broken.to_string());
+        lines.push(newer.clone());
+        fs::write(watchlist.datafile(), lines.join("\n") + "\n").unwrap();
+
+        watchlist.update(|video_item| {
+            video_item.priority = Some(1);
+            Ok(())
+        }).unwrap();
+        let removed = watchlist.remove(|video_item| video_item.work.title == "B").unwrap();
+        assert_eq!(removed.len(), 1);
+
+        let contents = fs::read_to_string(watchlist.datafile()).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        // still after the first item, and still last
+        assert_eq!(lines[3], broken);
+        assert_eq!(lines.last(), Some(&newer.as_str()));
+        let titles: Vec<String> = watchlist.iter().unwrap().filter_map(Result::ok).map(|vi| vi.work.title).collect();
+        assert_eq!(titles, ["A", "C"]);
+    }
+
+    #[test]
+    fn next_id_never_reuses_a_removed_id() {
+        let dir = TestDir::new("next-id");
+        let watchlist = dir.watchlist("");
+        for title in ["A", "B", "C"] {
+            watchlist.append(&mut series(title, Vec::new())).unwrap();
+        }
+        watchlist.remove(|video_item| video_item.id == 3).unwrap();
+        let mut d = series("D", Vec::new());
+        watchlist.append(&mut d).unwrap();
+        assert_eq!(d.id, 4);
+        watchlist.remove(|video_item| video_item.id >= 2).unwrap();
+        let mut e = series("E", Vec::new());
+        watchlist.append(&mut e).unwrap();
+        assert_eq!(e.id, 5);
+    }
+
+    #[test]
+    fn mal_and_anilist_statuses_read_back() {
+        let statuses = [
+            WatchStatus::Virgin,
+            WatchStatus::PlanToWatch,
+            WatchStatus::Partial,
+            WatchStatus::Rewatching(2),
+            WatchStatus::OnHold,
+            WatchStatus::Dropped,
+            WatchStatus::CaughtUp,
+            WatchStatus::Completed,
+        ];
+        for status in statuses {
+            assert_eq!(status.mal().parse::<WatchStatus>().unwrap().mal(), status.mal());
+            assert_eq!(status.anilist().parse::<WatchStatus>().unwrap().anilist(), status.anilist());
+        }
+    }
+
+    #[test]
+    fn positions_carry_over_season_boundaries() {
+        let episodes = [12, 13];
+        assert_eq!(position(1, Some(12)).carried_over(&episodes), position(1, Some(12)));
+        assert_eq!(position(1, Some(14)).carried_over(&episodes), position(2, Some(2)));
+        assert_eq!(position(1, Some(12)).advanced(3).carried_over(&episodes), position(2, Some(3)));
+        // past the last known season, as for an ongoing series, the position stays put
+        assert_eq!(position(2, Some(20)).carried_over(&episodes), position(2, Some(20)));
+        assert_eq!(position(1, None).carried_over(&episodes), position(1, None));
+
+        assert_eq!(position(1, Some(12)).episode_number(&episodes), Some(12));
+        assert_eq!(position(2, Some(2)).episode_number(&episodes), Some(14));
+        assert_eq!(position(1, None).episode_number(&episodes), Some(12));
+        assert_eq!(position(2, Some(20)).episode_number(&episodes), Some(25));
+        assert_eq!(position(3, Some(1)).episode_number(&episodes), None);
+    }
+}
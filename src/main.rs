@@ -1,108 +1,570 @@
-use std::{fs::{self, File, OpenOptions}, io::{self, Write as _, BufRead as _, BufReader, BufWriter}, path::Path};
+mod output;
+
+use std::{collections::{BTreeMap, BTreeSet}, io::{self, IsTerminal as _, Write as _}, path::PathBuf, process::ExitCode, str::FromStr};
 use chrono::NaiveDate;
-use clap::{Parser, Subcommand};
-use watchlist::{SiteData, VideoItem, VideoWork, VideoWorkMedium, WatchData, WatchPosition, WatchStatus};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use output::Output;
+use watchlist::{FieldAssignment, HistoryEvent, HistoryEventKind, Lock, LockMode, Opinion, RatingScale, Selector, SiteData, Stats, VideoItem, VideoWork, VideoWorkMedium, WatchData, WatchPosition, WatchStatus, Watchlist};
 
-fn main() {
+fn main() -> ExitCode {
     let config = Config::parse();
+    // recovering may have to bring back a data file lost by an older version
+    let watchlist = match (Watchlist::open(&config.datafile), &config.mode) {
+        (Ok(watchlist), _) => watchlist,
+        (Err(_), Mode::Recover { .. }) => Watchlist::new(&config.datafile),
+        (Err(e), _) => {
+            eprintln!("error opening datafile {e}");
+            let tempfile = config.tempfile.map(PathBuf::from)
+                .unwrap_or_else(|| Watchlist::new(&config.datafile).tempfile().to_path_buf());
+            if tempfile.exists() {
+                eprintln!("a temp file was left behind at {}; run `watchlist recover` to restore it", tempfile.display());
+            }
+            return ExitCode::FAILURE;
+        },
+    };
+    let watchlist = match &config.tempfile {
+        Some(tempfile) => watchlist.with_tempfile(tempfile),
+        None => watchlist,
+    };
+    // back-filling ids rewrites the data file, so it needs the exclusive lock too
+    let lock_mode = match (&config.mode, watchlist.has_missing_ids()) {
+        // checking must not change the file it checks, so ids are left missing
+        (Mode::Check, _) => LockMode::Shared,
+        (Mode::ListAll { .. } | Mode::ListDetails { .. } | Mode::UpNext | Mode::Remaining { .. } | Mode::History { .. } | Mode::Stats, Ok(false)) => LockMode::Shared,
+        (Mode::Tag { action: TagAction::List { .. } }, Ok(false)) => LockMode::Shared,
+        _ => LockMode::Exclusive,
+    };
+    let Some(lock) = lock(&watchlist, lock_mode, !config.no_wait) else { return ExitCode::FAILURE; };
+    if let Mode::Recover { restore, discard } = config.mode {
+        return recover(&watchlist, &lock, restore, discard);
+    }
+    if let Mode::Check = config.mode {
+        return check(&watchlist);
+    }
+    // before anything else rewrites, and so upgrades, the file without a backup
+    if let Mode::Migrate = config.mode {
+        return migrate(&watchlist, false);
+    }
+    // rewrites only happen under the exclusive lock, so while holding any lock a temp file is stale
+    if watchlist.tempfile().exists() {
+        eprintln!("{}; run `watchlist recover` to deal with it", watchlist::Error::StaleTempfile(watchlist.tempfile().to_path_buf()));
+        if let Mode::Append { .. } | Mode::Edit { .. } | Mode::Advance { .. } | Mode::Remove { .. } | Mode::Tag { action: TagAction::Add { .. } | TagAction::Remove { .. } } = config.mode {
+            return ExitCode::FAILURE;
+        }
+    }
+    // any rewrite would upgrade an older file; do it up front so the backup gets reported
+    if lock_mode == LockMode::Exclusive && watchlist.needs_migration().unwrap_or(false) && migrate(&watchlist, true) != ExitCode::SUCCESS {
+        return ExitCode::FAILURE;
+    }
+    if let Err(e) = watchlist.assign_missing_ids() {
+        eprintln!("error assigning ids to existing items {e}");
+        return ExitCode::FAILURE;
+    }
     match config.mode {
-        Mode::ListAll => {
-            match File::open(&config.datafile) {
-                Err(e) => eprintln!("error opening datafile {e:?}"),
-                Ok(f) => {
-                    for video_item in FileVideoItems::new(&f) {
-                        println!("{video_item:#?}");
-                    }
+        Mode::ListAll { filter, sort } => {
+            let video_items = sort.sorted(video_items(&watchlist)
+                .filter(|vi| filter.matches(vi, config.rating_scale)));
+            config.output.print_video_items(&video_items, config.rating_scale);
+            ExitCode::SUCCESS
+        },
+        Mode::UpNext => {
+            let mut video_items: Vec<VideoItem> = video_items(&watchlist)
+                .filter(|vi| matches!(vi.watch_data.status, WatchStatus::Partial | WatchStatus::Rewatching(_) | WatchStatus::PlanToWatch))
+                .collect();
+            video_items.sort_by(VideoItem::queue_order);
+            config.output.print_up_next(&video_items, config.rating_scale);
+            ExitCode::SUCCESS
+        },
+        Mode::Stats => {
+            let video_items: Vec<VideoItem> = video_items(&watchlist).collect();
+            config.output.print_stats(&Stats::new(&video_items));
+            ExitCode::SUCCESS
+        },
+        Mode::Append { fields } => {
+            let mut video_item = match create_video_item(fields, config.rating_scale) {
+                Err(e) => {
+                    eprintln!("error creating data {e}");
+                    return ExitCode::FAILURE;
                 },
+                Ok(video_item) => video_item,
+            };
+            match watchlist.append(&mut video_item) {
+                Err(e) => {
+                    eprintln!("error appending to datafile {e}");
+                    ExitCode::FAILURE
+                },
+                Ok(()) => ExitCode::SUCCESS,
             }
         },
-        Mode::Append => {
-            match create_video_item() {
-                Err(e) => eprintln!("error creating data {e:?}"),
-                Ok(video_item) => match OpenOptions::new().append(true).open(&config.datafile) {
-                    Err(e) => eprintln!("error opening datafile {e:?}"),
-                    Ok(mut f) => match write_video_item_to_file(&mut f, &video_item) {
-                        Ok(()) => (),
-                        Err(Error::IO(e)) => eprintln!("error writing to file {e:?}"),
-                        Err(Error::RON(e)) => eprintln!("error stringifying work {e:?} {video_item:?}"),
-                    }
-                }
-            }
+        Mode::ListDetails { name, sort } => {
+            let Some(selector) = select(&watchlist, &name, config.exact) else { return ExitCode::FAILURE; };
+            let video_items = sort.sorted(ranked(&watchlist, &selector).into_iter());
+            config.output.print_video_items(&video_items, config.rating_scale);
+            ExitCode::SUCCESS
         },
-        Mode::ListDetails { name } => {
-            match File::open(&config.datafile) {
-                Err(e) => eprintln!("error opening datafile {e:?}"),
-                Ok(f) => {
-                    for video_item in FileVideoItems::new(&f)
-                        .filter(|vi| vi.work.title == name)
-                    {
-                        println!("{video_item:#?}");
+        Mode::Edit { name, set, all } => {
+            let set = match set.iter().map(|s| FieldAssignment::parse(s, config.rating_scale)).collect::<Result<Vec<_>, _>>() {
+                Err(e) => {
+                    eprintln!("error {e}");
+                    return ExitCode::FAILURE;
+                },
+                Ok(set) => set,
+            };
+            let Some(selector) = select(&watchlist, &name, config.exact) else { return ExitCode::FAILURE; };
+            let Some(ids) = choose(&watchlist, &selector, all) else { return ExitCode::FAILURE; };
+            let today = chrono::Local::now().date_naive();
+            let result = watchlist.update(|video_item| {
+                if ids.contains(&video_item.id) {
+                    let before = video_item.watch_data.clone();
+                    match set.is_empty() {
+                        true => edit_video_item(video_item, config.rating_scale)?,
+                        false => for assignment in &set {
+                            assignment.apply(video_item);
+                        },
                     }
+                    video_item.reconcile_status();
+                    video_item.catch_up();
+                    video_item.record_changes(&before, today);
+                }
+                Ok(())
+            });
+            match result {
+                Err(e) => {
+                    eprintln!("error editing; data file was not overwritten: {e}");
+                    ExitCode::FAILURE
                 },
+                Ok(()) => ExitCode::SUCCESS,
             }
         },
-        Mode::Edit { name } => {
-            match File::open(&config.datafile) {
-                Err(e) => eprintln!("error opening datafile {e:?}"),
-                Ok(f) => {
-                    let datafile_path = Path::new(&config.datafile);
-                    let tempfile_path = Path::new(&config.tempfile);
-                    match edit_video_items_by_name_to_temp_file(
-                        FileVideoItems::new(&f),
-                        &name,
-                        tempfile_path,
-                    ) {
-                        Ok(true) => println!("encountered an error during temp file creation; will not overwrite data file"),
-                        Ok(false) => {
-                            match fs::remove_file(datafile_path) {
-                                Err(e) => eprintln!("error removing data file {e:?}"),
-                                Ok(()) => match fs::rename(tempfile_path, datafile_path) {
-                                    Err(e) => eprintln!("error renaming temp file to data file {e:?}"),
-                                    Ok(()) => (),
-                                }
+        Mode::Advance { name, episodes, all } => {
+            let Some(selector) = select(&watchlist, &name, config.exact) else { return ExitCode::FAILURE; };
+            let Some(ids) = choose(&watchlist, &selector, all) else { return ExitCode::FAILURE; };
+            let today = chrono::Local::now().date_naive();
+            let mut advanced_all = true;
+            let result = watchlist.update(|video_item| {
+                if ids.contains(&video_item.id) {
+                    match video_item.advance(episodes, today) {
+                        true => println!("{}", output::summary(video_item)),
+                        false => {
+                            advanced_all = false;
+                            match video_item.work.medium {
+                                VideoWorkMedium::Movie => eprintln!("{} is a movie; it has no episodes to advance", video_item.work.title),
+                                _ => eprintln!("nothing left to watch; {}", output::summary(video_item)),
                             }
                         },
-                        Err(e) => eprintln!("error creating temp file {e:?}"),
                     }
+                }
+                Ok(())
+            });
+            match result {
+                Err(e) => {
+                    eprintln!("error advancing; data file was not overwritten: {e}");
+                    ExitCode::FAILURE
                 },
+                Ok(()) if !advanced_all => ExitCode::FAILURE,
+                Ok(()) => ExitCode::SUCCESS,
             }
         },
-        Mode::Remove { name } => {
-            match File::open(&config.datafile) {
-                Err(e) => eprintln!("error opening datafile {e:?}"),
-                Ok(f) => {
-                    let datafile_path = Path::new(&config.datafile);
-                    let tempfile_path = Path::new(&config.tempfile);
-                    match remove_video_items_by_name_to_temp_file(
-                        FileVideoItems::new(&f),
-                        &name,
-                        tempfile_path,
-                    ) {
-                        Ok(true) => println!("encountered an error during temp file creation; will not overwrite data file"),
-                        Ok(false) => {
-                            match fs::remove_file(datafile_path) {
-                                Err(e) => eprintln!("error removing data file {e:?}"),
-                                Ok(()) => match fs::rename(tempfile_path, datafile_path) {
-                                    Err(e) => eprintln!("error renaming temp file to data file {e:?}"),
-                                    Ok(()) => (),
-                                }
-                            }
-                        },
-                        Err(e) => eprintln!("error creating temp file {e:?}"),
+        Mode::Remaining { name } => {
+            let video_items = match name {
+                Some(name) => {
+                    let Some(selector) = select(&watchlist, &name, config.exact) else { return ExitCode::FAILURE; };
+                    ranked(&watchlist, &selector)
+                },
+                None => video_items(&watchlist)
+                    .filter(|vi| matches!(vi.watch_data.status, WatchStatus::Partial | WatchStatus::Rewatching(_) | WatchStatus::OnHold))
+                    .collect(),
+            };
+            config.output.print_remaining(&video_items);
+            ExitCode::SUCCESS
+        },
+        Mode::History { name } => {
+            let Some(selector) = select(&watchlist, &name, config.exact) else { return ExitCode::FAILURE; };
+            let video_items = ranked(&watchlist, &selector);
+            if video_items.is_empty() {
+                eprintln!("no item with {selector}");
+                return ExitCode::FAILURE;
+            }
+            config.output.print_history(&video_items);
+            ExitCode::SUCCESS
+        },
+        Mode::Tag { action: TagAction::List { name: None } } => {
+            let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+            for video_item in video_items(&watchlist) {
+                for tag in video_item.tags {
+                    *counts.entry(tag).or_default() += 1;
+                }
+            }
+            config.output.print_tag_counts(&counts);
+            ExitCode::SUCCESS
+        },
+        Mode::Tag { action: TagAction::List { name: Some(name) } } => {
+            let Some(selector) = select(&watchlist, &name, config.exact) else { return ExitCode::FAILURE; };
+            config.output.print_item_tags(&ranked(&watchlist, &selector));
+            ExitCode::SUCCESS
+        },
+        Mode::Tag { action: TagAction::Add { name, tags, all } } => {
+            retag(&watchlist, &name, config.exact, all, |item_tags| item_tags.extend(tags.iter().cloned()))
+        },
+        Mode::Tag { action: TagAction::Remove { name, tags, all } } => {
+            retag(&watchlist, &name, config.exact, all, |item_tags| item_tags.retain(|tag| !tags.contains(tag)))
+        },
+        Mode::Remove { name, all } => {
+            let Some(selector) = select(&watchlist, &name, config.exact) else { return ExitCode::FAILURE; };
+            let Some(ids) = choose(&watchlist, &selector, all) else { return ExitCode::FAILURE; };
+            match watchlist.remove(|video_item| ids.contains(&video_item.id)) {
+                Err(e) => {
+                    eprintln!("error removing; data file was not overwritten: {e}");
+                    ExitCode::FAILURE
+                },
+                Ok(removed) => {
+                    for video_item in removed {
+                        println!("removed {}", output::describe(&video_item));
                     }
+                    ExitCode::SUCCESS
                 },
             }
         },
+        Mode::Recover { .. } | Mode::Check | Mode::Migrate => unreachable!("handled before ids are assigned"),
     }
 }
 
+/// changes the tags of the items `name` refers to
+fn retag(watchlist: &Watchlist, name: &str, exact: bool, all: bool, change: impl Fn(&mut BTreeSet<String>)) -> ExitCode {
+    let Some(selector) = select(watchlist, name, exact) else { return ExitCode::FAILURE; };
+    let Some(ids) = choose(watchlist, &selector, all) else { return ExitCode::FAILURE; };
+    let result = watchlist.update(|video_item| {
+        if ids.contains(&video_item.id) {
+            change(&mut video_item.tags);
+        }
+        Ok(())
+    });
+    match result {
+        Err(e) => {
+            eprintln!("error tagging; data file was not overwritten: {e}");
+            ExitCode::FAILURE
+        },
+        Ok(()) => ExitCode::SUCCESS,
+    }
+}
+
+/// prints every problem in the data file; fails with status 1 if there are any, for use in hooks,
+/// and with status 2 if the file cannot be checked
+fn check(watchlist: &Watchlist) -> ExitCode {
+    let problems = match watchlist.check(chrono::Local::now().date_naive()) {
+        Err(e) => {
+            eprintln!("error checking datafile {e}");
+            return ExitCode::from(2);
+        },
+        Ok(problems) => problems,
+    };
+    for problem in &problems {
+        println!("{problem}");
+    }
+    match problems.is_empty() {
+        true => ExitCode::SUCCESS,
+        false => {
+            eprintln!("{} problems found", problems.len());
+            ExitCode::FAILURE
+        },
+    }
+}
+
+/// `implicit` when upgrading ahead of another command, whose output on stdout must stay clean,
+/// so the notice goes to stderr
+fn migrate(watchlist: &Watchlist, implicit: bool) -> ExitCode {
+    let version = match watchlist.format_version() {
+        Err(e) => {
+            eprintln!("error reading datafile {e}");
+            return ExitCode::FAILURE;
+        },
+        Ok(version) => version,
+    };
+    let notice = match watchlist.migrate() {
+        Err(e) => {
+            eprintln!("error migrating; data file was not overwritten: {e}");
+            return ExitCode::FAILURE;
+        },
+        Ok(None) => format!("data file is already in the current format (version {})", watchlist::CURRENT_VERSION),
+        Ok(Some(backup)) if version == watchlist::CURRENT_VERSION => format!(
+            "marked data file as format version {version}; the original is at {}",
+            backup.display(),
+        ),
+        Ok(Some(backup)) => format!(
+            "migrated data file from format version {version} to {}; the original is at {}",
+            watchlist::CURRENT_VERSION,
+            backup.display(),
+        ),
+    };
+    match implicit {
+        true => eprintln!("{notice}"),
+        false => println!("{notice}"),
+    }
+    if let Err(e) = watchlist.assign_missing_ids() {
+        eprintln!("error assigning ids to existing items {e}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+/// reports on a temp file left behind by an interrupted rewrite, then restores or discards it
+/// as asked, or as the user answers
+fn recover(watchlist: &Watchlist, lock: &Lock, restore: bool, discard: bool) -> ExitCode {
+    let report = match watchlist.inspect_tempfile() {
+        Err(e) => {
+            eprintln!("error inspecting temp file {e}");
+            return ExitCode::FAILURE;
+        },
+        Ok(report) => report,
+    };
+    let describe = |summary: &watchlist::FileSummary| {
+        let modified = summary.modified
+            .map(|modified| chrono::DateTime::<chrono::Local>::from(modified).format(", modified %Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        let incomplete = match summary.ends_with_newline {
+            true => "",
+            false => ", last line cut short",
+        };
+        format!("{} items, {} unparseable lines{incomplete}{modified}", summary.items, summary.unparseable_lines)
+    };
+    let Some(tempfile) = &report.tempfile else {
+        println!("no temp file at {}; nothing to recover", watchlist.tempfile().display());
+        return ExitCode::SUCCESS;
+    };
+    println!("temp file {}: {}", watchlist.tempfile().display(), describe(tempfile));
+    match &report.datafile {
+        Some(datafile) => println!("data file {}: {}", watchlist.datafile().display(), describe(datafile)),
+        None => println!("data file {}: missing", watchlist.datafile().display()),
+    }
+    if let Some(pid) = lock.crashed_holder() {
+        println!("left behind by pid {pid}, which exited without releasing the watchlist");
+    }
+    if report.identical {
+        println!("the temp file is identical to the data file");
+    }
+    // a missing data file means an older version crashed between removing it and renaming the
+    // temp file; otherwise the data file was never touched and is the safer choice
+    let restore = match (restore, discard) {
+        (true, _) => true,
+        (_, true) => false,
+        _ if report.identical => false,
+        _ => {
+            let suggestion = match report.datafile {
+                None => "restore",
+                Some(_) => "discard",
+            };
+            match ask_recovery(suggestion) {
+                Err(e) => {
+                    eprintln!("error reading choice {e:?}");
+                    return ExitCode::FAILURE;
+                },
+                Ok(None) => {
+                    println!("left the temp file in place");
+                    return ExitCode::SUCCESS;
+                },
+                Ok(Some(restore)) => restore,
+            }
+        },
+    };
+    let result = match restore {
+        true => watchlist.recover_tempfile()
+            .map(|()| println!("restored the temp file as the data file"))
+            .map_err(|e| eprintln!("error restoring temp file {e}")),
+        false => watchlist.discard_tempfile()
+            .map(|()| println!("removed the temp file; the data file is unchanged"))
+            .map_err(|e| eprintln!("error removing temp file {e}")),
+    };
+    match result {
+        Err(()) => ExitCode::FAILURE,
+        Ok(()) => ExitCode::SUCCESS,
+    }
+}
+
+/// `Ok(Some(true))` to restore, `Ok(Some(false))` to discard, `Ok(None)` to leave things as they are
+fn ask_recovery(suggestion: &str) -> Result<Option<bool>, io::Error> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    loop {
+        print!("restore the temp file over the data file, discard it, or leave it (suggested: {suggestion})? (restore/discard/leave) ");
+        stdout.flush()?;
+        let mut inp = String::new();
+        if stdin.read_line(&mut inp)? == 0 {
+            return Ok(None);
+        }
+        match inp.trim() {
+            "restore" => return Ok(Some(true)),
+            "discard" => return Ok(Some(false)),
+            "leave" | "" => return Ok(None),
+            _ => continue,
+        }
+    }
+}
+
+/// `None` if the watchlist could not be locked, e.g. because another command holds it and `wait`
+/// is not given; the reason has been reported
+fn lock(watchlist: &Watchlist, mode: LockMode, wait: bool) -> Option<Lock> {
+    let result = match watchlist.lock(mode, false) {
+        Err(e @ watchlist::Error::Locked { .. }) if wait => {
+            eprintln!("{e}; waiting for it to finish");
+            watchlist.lock(mode, true)
+        },
+        result => result,
+    };
+    match result {
+        Err(e) => {
+            eprintln!("error locking watchlist {e}");
+            None
+        },
+        Ok(lock) => Some(lock),
+    }
+}
+
+fn select(watchlist: &Watchlist, name: &str, exact: bool) -> Option<Selector> {
+    match watchlist.select(name, exact) {
+        Err(e @ watchlist::Error::Ambiguous(id)) => {
+            eprintln!("{e}; use #{id} for the id, or --exact for the title");
+            None
+        },
+        Err(e) => {
+            eprintln!("error reading datafile {e}");
+            None
+        },
+        Ok(selector) => Some(selector),
+    }
+}
+
+/// the ids of the items `selector` refers to; without asking only for an id, a single title that
+/// matches in full (rank 0), or every such title if `all` is given; otherwise asks which are
+/// meant, loose matches included, or fails if stdin is not a terminal; `None` if nothing is to
+/// be done, which has been reported
+fn choose(watchlist: &Watchlist, selector: &Selector, all: bool) -> Option<Vec<u32>> {
+    let candidates = ranked(watchlist, selector);
+    if candidates.is_empty() {
+        eprintln!("no item with {selector}");
+        return None;
+    }
+    let exact: Vec<u32> = candidates.iter()
+        .filter(|vi| selector.rank(vi) == Some(0))
+        .map(|vi| vi.id)
+        .collect();
+    if exact.len() == 1 || (all && !exact.is_empty()) {
+        return Some(exact);
+    }
+    // nobody is there to answer, e.g. in a script
+    if !io::stdin().is_terminal() {
+        for vi in &candidates {
+            eprintln!("  {}", output::describe(vi));
+        }
+        match exact.is_empty() {
+            true => eprintln!("error no item has that very title, only {} like it; give an #id", candidates.len()),
+            false => eprintln!("error {} items have that title; give an #id, or pass --all to act on every one", exact.len()),
+        }
+        return None;
+    }
+    match choose_candidates(&candidates) {
+        Err(e) => {
+            eprintln!("error reading choice {e:?}");
+            None
+        },
+        Ok(None) => {
+            match exact.is_empty() {
+                true => eprintln!("nothing chosen; no item has that very title"),
+                false => eprintln!("nothing chosen; pass --all to act on every item with that title"),
+            }
+            None
+        },
+        Ok(Some(ids)) => Some(ids),
+    }
+}
+
+/// the items `selector` matches, best match first
+fn ranked(watchlist: &Watchlist, selector: &Selector) -> Vec<VideoItem> {
+    let mut ranked: Vec<(u32, VideoItem)> = video_items(watchlist)
+        .filter_map(|vi| selector.rank(&vi).map(|rank| (rank, vi)))
+        .collect();
+    ranked.sort_by_key(|(rank, _)| *rank);
+    ranked.into_iter().map(|(_, vi)| vi).collect()
+}
+
+/// `Ok(None)` if the user gives up (an empty line or end of input)
+fn choose_candidates(candidates: &[VideoItem]) -> Result<Option<Vec<u32>>, io::Error> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    for (i, vi) in candidates.iter().enumerate() {
+        println!("{}. {}", i + 1, output::describe(vi));
+    }
+    loop {
+        print!("which (e.g. 1, 1 3, all)? ");
+        stdout.flush()?;
+        let mut inp = String::new();
+        if stdin.read_line(&mut inp)? == 0 {
+            return Ok(None);
+        }
+        match inp.trim() {
+            "" => return Ok(None),
+            "all" => return Ok(Some(candidates.iter().map(|vi| vi.id).collect())),
+            inp => {
+                let chosen: Option<Vec<u32>> = inp.split([' ', ','])
+                    .filter(|choice| !choice.is_empty())
+                    .map(|choice| match choice.parse::<usize>() {
+                        Ok(n) if (1..=candidates.len()).contains(&n) => Some(candidates[n - 1].id),
+                        _ => None,
+                    })
+                    .collect();
+                match chosen {
+                    Some(ids) => return Ok(Some(ids)),
+                    None => continue,
+                }
+            },
+        }
+    }
+}
+
+/// the parseable items of the watchlist; errors are reported and skipped
+fn video_items(watchlist: &Watchlist) -> impl Iterator<Item = VideoItem> {
+    let iter = match watchlist.iter() {
+        Err(e) => {
+            eprintln!("error opening datafile {e}");
+            None
+        },
+        Ok(iter) => Some(iter),
+    };
+    iter.into_iter().flatten().filter_map(|video_item| match video_item {
+        Err(e) => {
+            eprintln!("{e}");
+            None
+        },
+        Ok(video_item) => Some(video_item),
+    })
+}
+
 #[derive(Parser)]
 struct Config {
     #[arg(short, long, default_value = "watchlist.ron")]
     datafile: String,
 
-    #[arg(short, long, default_value = "watchlist.temp.ron")]
-    tempfile: String,
+    /// [default: <datafile stem>.temp.ron next to the data file]
+    #[arg(short, long)]
+    tempfile: Option<String>,
+
+    /// wait for other running watchlist commands to finish (the default)
+    #[arg(long, global = true, overrides_with = "no_wait")]
+    wait: bool,
+
+    /// fail right away if another running watchlist command holds the watchlist
+    #[arg(long, global = true, overrides_with = "wait")]
+    no_wait: bool,
+
+    /// match titles exactly, including case and accents, instead of loosely
+    #[arg(long, global = true)]
+    exact: bool,
+
+    /// how ratings are entered and shown: 10 for 1 to 10, or stars for 0.5 to 5 stars
+    #[arg(long, global = true, env = "WATCHLIST_RATING_SCALE", default_value = "10")]
+    rating_scale: RatingScale,
+
+    #[command(flatten)]
+    output: Output,
 
     #[command(subcommand)]
     mode: Mode,
@@ -112,28 +574,310 @@ struct Config {
 enum Mode {
     /// [alias l]
     #[command(alias = "l")]
-    ListAll,
+    ListAll {
+        #[command(flatten)]
+        filter: Filter,
+        #[command(flatten)]
+        sort: Sort,
+    },
     /// [alias d]
     #[command(alias = "d")]
     ListDetails {
+        /// title, or #id
         name: String,
+        #[command(flatten)]
+        sort: Sort,
     },
+    /// what is being watched or planned, with the next episode; by priority, then most recently updated
+    UpNext,
+    /// counts by medium, status, month updated and release year
+    Stats,
     /// [alias a]
     #[command(alias = "a")]
-    Append,
+    Append {
+        #[command(flatten)]
+        fields: NewItemFields,
+    },
     /// [alias e]
     #[command(alias = "e")]
     Edit {
+        /// title, or #id
         name: String,
+        /// set a field without the menus, e.g. `--set status=partial --set position=1:12`;
+        /// fields: title, year, medium, episodes, tracker, watch, status, position, rating,
+        /// season-rating (as season:rating), review, priority, ongoing, updated
+        #[arg(long, value_name = "FIELD=VALUE")]
+        set: Vec<String>,
+        /// edit every item with this title instead of asking which
+        #[arg(long)]
+        all: bool,
+    },
+    /// mark the next episode(s) as watched [alias n]
+    #[command(alias = "n")]
+    Advance {
+        /// title, or #id
+        name: String,
+        #[arg(default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+        episodes: u16,
+        /// advance every item with this title instead of asking which
+        #[arg(long)]
+        all: bool,
+    },
+    /// how many of the known episodes are left to watch
+    Remaining {
+        /// title, or #id; every series being watched if left out
+        name: Option<String>,
+    },
+    /// show when an item was added, started, watched and completed
+    History {
+        /// title, or #id
+        name: String,
+    },
+    /// group items with free-form tags
+    Tag {
+        #[command(subcommand)]
+        action: TagAction,
     },
     /// [alias r]
     #[command(alias = "r")]
     Remove {
+        /// title, or #id
+        name: String,
+        /// remove every item with this title instead of asking which
+        #[arg(long)]
+        all: bool,
+    },
+    /// report syntax errors and inconsistent items; exits non-zero if there are any
+    Check,
+    /// upgrade the data file to the current format, keeping a backup of the original
+    Migrate,
+    /// deal with a temp file left behind by an interrupted edit or remove
+    Recover {
+        /// replace the data file with the temp file without asking
+        #[arg(long, conflicts_with = "discard")]
+        restore: bool,
+        /// delete the temp file without asking
+        #[arg(long)]
+        discard: bool,
+    }
+}
+
+#[derive(Subcommand)]
+enum TagAction {
+    Add {
+        /// title, or #id
         name: String,
+        /// e.g. with-partner; spaces become dashes
+        #[arg(required = true, value_parser = VideoItem::parse_tag)]
+        tags: Vec<String>,
+        /// tag every item with this title instead of asking which
+        #[arg(long)]
+        all: bool,
+    },
+    Remove {
+        /// title, or #id
+        name: String,
+        #[arg(required = true, value_parser = VideoItem::parse_tag)]
+        tags: Vec<String>,
+        /// untag every item with this title instead of asking which
+        #[arg(long)]
+        all: bool,
+    },
+    /// every tag in use with how many items have it, or the tags of the items with a title
+    List {
+        /// title, or #id
+        name: Option<String>,
+    },
+}
+
+/// listing filters; an item is listed only if it passes every given filter
+#[derive(Args)]
+struct Filter {
+    /// only items with this status (`rewatching` matches any rewatch); may be repeated to allow several
+    #[arg(long)]
+    status: Vec<WatchStatus>,
+    /// only items of this medium; may be repeated to allow several
+    #[arg(long)]
+    medium: Vec<VideoWorkMedium>,
+    /// only series still coming out
+    #[arg(long, conflicts_with = "finished")]
+    ongoing: bool,
+    /// only series that have finished; movies are neither ongoing nor finished
+    #[arg(long)]
+    finished: bool,
+    /// release year or inclusive range, e.g. 2015, 2010..2020, 2010.. or ..2020
+    #[arg(long)]
+    year: Option<YearRange>,
+    /// only items last updated before this date (yyyy-mm-dd)
+    #[arg(long)]
+    updated_before: Option<NaiveDate>,
+    /// only items last updated after this date (yyyy-mm-dd)
+    #[arg(long)]
+    updated_after: Option<NaiveDate>,
+    #[arg(long)]
+    has_tracker: bool,
+    /// only items rated at least this, on the rating scale
+    #[arg(long)]
+    min_rating: Option<f32>,
+    /// only items with this tag; may be repeated to allow several
+    #[arg(long, value_parser = VideoItem::parse_tag)]
+    tag: Vec<String>,
+}
+
+impl Filter {
+    fn matches(&self, video_item: &VideoItem, scale: RatingScale) -> bool {
+        (self.status.is_empty() || self.status.iter().any(|status| status.same_kind(&video_item.watch_data.status)))
+            && (self.medium.is_empty() || self.medium.contains(&video_item.work.medium))
+            // as in `Stats`, movies are neither
+            && (!self.ongoing || (video_item.ongoing && video_item.work.medium != VideoWorkMedium::Movie))
+            && (!self.finished || (!video_item.ongoing && video_item.work.medium != VideoWorkMedium::Movie))
+            && self.year.as_ref().is_none_or(|year| year.contains(video_item.work.year))
+            && self.updated_before.is_none_or(|date| video_item.updated < date)
+            && self.updated_after.is_none_or(|date| video_item.updated > date)
+            && (!self.has_tracker || video_item.site_data.tracker.is_some())
+            && (self.tag.is_empty() || self.tag.iter().any(|tag| video_item.tags.contains(tag)))
+            && self.min_rating.is_none_or(|min| video_item.opinion.rating.is_some_and(|rating| scale.value(rating) >= min))
+    }
+}
+
+/// listing order; unless `--sort` is given, file order or, when looking up a title, best match first
+#[derive(Args)]
+struct Sort {
+    #[arg(long, value_enum)]
+    sort: Option<SortKey>,
+    #[arg(long)]
+    reverse: bool,
+}
+
+#[derive(Clone, ValueEnum)]
+enum SortKey {
+    /// ignoring leading articles and case
+    Title,
+    Year,
+    Medium,
+    /// then by position
+    Status,
+    Updated,
+    /// as in the queue: by priority, then most recently updated
+    Priority,
+}
+
+impl Sort {
+    fn sorted(&self, video_items: impl Iterator<Item = VideoItem>) -> Vec<VideoItem> {
+        let mut video_items: Vec<VideoItem> = video_items.collect();
+        match self.sort {
+            None => (),
+            Some(SortKey::Title) => video_items.sort_by_cached_key(|vi| vi.work.sort_title()),
+            Some(SortKey::Year) => video_items.sort_by_key(|vi| vi.work.year),
+            Some(SortKey::Medium) => video_items.sort_by(|a, b| a.work.medium.cmp(&b.work.medium)),
+            Some(SortKey::Status) => video_items.sort_by(|a, b| a.watch_data.status.cmp(&b.watch_data.status)
+                .then_with(|| a.watch_data.position.cmp(&b.watch_data.position))),
+            Some(SortKey::Updated) => video_items.sort_by_key(|vi| vi.updated),
+            Some(SortKey::Priority) => video_items.sort_by(VideoItem::queue_order),
+        }
+        if self.reverse {
+            video_items.reverse();
+        }
+        video_items
+    }
+}
+
+#[derive(Clone)]
+struct YearRange {
+    from: Option<u16>,
+    to: Option<u16>,
+}
+
+impl YearRange {
+    fn contains(&self, year: u16) -> bool {
+        self.from.is_none_or(|from| from <= year) && self.to.is_none_or(|to| year <= to)
+    }
+}
+
+impl FromStr for YearRange {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |year: &str| match year.trim() {
+            "" => Ok(None),
+            year => year.parse::<u16>().map(Some).map_err(|_| format!("invalid year \"{year}\"")),
+        };
+        match s.split_once("..") {
+            Some((from, to)) => Ok(Self { from: parse(from)?, to: parse(to)? }),
+            None => {
+                let year = parse(s)?;
+                Ok(Self { from: year, to: year })
+            },
+        }
+    }
+}
+
+/// fields given on the command line for a new item; anything left out is prompted for
+#[derive(Args)]
+struct NewItemFields {
+    #[arg(long)]
+    title: Option<String>,
+    #[arg(long)]
+    year: Option<u16>,
+    /// movie, tvshow or anime
+    #[arg(long)]
+    medium: Option<VideoWorkMedium>,
+    /// episodes in each season, e.g. 12,12,24
+    #[arg(long, value_delimiter = ',')]
+    episodes: Vec<u16>,
+    /// virgin, plan-to-watch, partial, rewatching[:N], on-hold, dropped, caught-up or completed
+    #[arg(long)]
+    status: Option<WatchStatus>,
+    /// last watched position, e.g. S2E5 or S2 for a whole season
+    #[arg(long)]
+    position: Option<WatchPosition>,
+    /// `--ongoing` alone means true
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    ongoing: Option<bool>,
+    #[arg(long)]
+    tracker: Option<String>,
+    #[arg(long)]
+    watch: Option<String>,
+    /// on the rating scale
+    #[arg(long)]
+    rating: Option<String>,
+    #[arg(long)]
+    review: Option<String>,
+    /// may be repeated
+    #[arg(long = "tag", value_parser = VideoItem::parse_tag)]
+    tags: Vec<String>,
+    /// place in the queue of what to watch next, 1 first
+    #[arg(long)]
+    priority: Option<u16>,
+}
+
+/// like `Stdin::read_line`, but fails at the end of input instead of reading empty lines forever
+fn read_line(stdin: &io::Stdin, line: &mut String) -> Result<usize, io::Error> {
+    match stdin.read_line(line)? {
+        0 => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected end of input")),
+        n => Ok(n),
     }
 }
 
-fn edit_video_item(video_item: &mut VideoItem) -> Result<(), io::Error> {
+impl NewItemFields {
+    /// the flags for fields `create_video_item` would have to prompt for
+    fn missing(&self) -> Vec<&'static str> {
+        let series = !matches!(self.medium, Some(VideoWorkMedium::Movie));
+        let started = self.status.as_ref().is_some_and(WatchStatus::started);
+        [
+            (self.title.is_none(), "--title"),
+            (self.year.is_none(), "--year"),
+            (self.medium.is_none(), "--medium"),
+            (self.status.is_none(), "--status"),
+            (series && started && self.position.is_none(), "--position"),
+            (series && self.ongoing.is_none(), "--ongoing"),
+        ]
+            .into_iter()
+            .filter_map(|(missing, flag)| missing.then_some(flag))
+            .collect()
+    }
+}
+
+fn edit_video_item(video_item: &mut VideoItem, scale: RatingScale) -> Result<(), io::Error> {
     let stdin = io::stdin();
     let mut stdout = io::stdout();
     loop {
@@ -143,26 +887,29 @@ fn edit_video_item(video_item: &mut VideoItem) -> Result<(), io::Error> {
                 3. watch data\n\
                 4. ongoing\n\
                 5. updated\n\
+                6. rating and review\n\
+                7. priority\n\
                 > ");
         stdout.flush()?;
         let mut inp = String::new();
-        stdin.read_line(&mut inp)?;
+        read_line(&stdin, &mut inp)?;
         match inp.trim() {
             "1" => loop {
                 println!("{:#?}", video_item.work);
                 print!("1. title\n\
                         2. year\n\
                         3. medium\n\
+                        4. episodes per season\n\
                         > ");
                 stdout.flush()?;
                 let mut inp = String::new();
-                stdin.read_line(&mut inp)?;
+                read_line(&stdin, &mut inp)?;
                 match inp.trim() {
                     "1" => {
                         print!("title: ");
                         stdout.flush()?;
                         let mut title = String::new();
-                        stdin.read_line(&mut title)?;
+                        read_line(&stdin, &mut title)?;
                         if !title.trim().is_empty() {
                             video_item.work.title = title.trim().to_string();
                         }
@@ -171,7 +918,7 @@ fn edit_video_item(video_item: &mut VideoItem) -> Result<(), io::Error> {
                         print!("year: ");
                         stdout.flush()?;
                         let mut inp = String::new();
-                        stdin.read_line(&mut inp)?;
+                        read_line(&stdin, &mut inp)?;
                         if inp.trim().is_empty() {
                             break;
                         }
@@ -184,7 +931,7 @@ fn edit_video_item(video_item: &mut VideoItem) -> Result<(), io::Error> {
                         print!("medium (movie, tvshow, anime): ");
                         stdout.flush()?;
                         let mut inp = String::new();
-                        stdin.read_line(&mut inp)?;
+                        read_line(&stdin, &mut inp)?;
                         match inp.trim() {
                             "movie" => {
                                 video_item.work.medium = VideoWorkMedium::Movie;
@@ -202,6 +949,23 @@ fn edit_video_item(video_item: &mut VideoItem) -> Result<(), io::Error> {
                             _ => continue,
                         }
                     },
+                    "4" => loop {
+                        print!("episodes per season (e.g. 12,12,24, or none): ");
+                        stdout.flush()?;
+                        let mut inp = String::new();
+                        read_line(&stdin, &mut inp)?;
+                        match inp.trim() {
+                            "" => break,
+                            "none" => {
+                                video_item.work.episodes = Vec::new();
+                                break;
+                            },
+                            inp => if let Ok(episodes) = VideoWork::parse_episodes(inp) {
+                                video_item.work.episodes = episodes;
+                                break;
+                            },
+                        }
+                    },
                     "" => break,
                     _ => continue,
                 }
@@ -213,19 +977,19 @@ fn edit_video_item(video_item: &mut VideoItem) -> Result<(), io::Error> {
                         > ");
                 stdout.flush()?;
                 let mut inp = String::new();
-                stdin.read_line(&mut inp)?;
+                read_line(&stdin, &mut inp)?;
                 match inp.trim() {
                     "1" => loop {
                         print!("tracker (true/false)? ");
                         stdout.flush()?;
                         let mut inp = String::new();
-                        stdin.read_line(&mut inp)?;
+                        read_line(&stdin, &mut inp)?;
                         match inp.trim() {
                             "true" => {
                                 print!("tracker: ");
                                 stdout.flush()?;
                                 let mut tracker = String::new();
-                                stdin.read_line(&mut tracker)?;
+                                read_line(&stdin, &mut tracker)?;
                                 if !tracker.trim().is_empty() {
                                     video_item.site_data.tracker = Some(tracker.trim().to_string())
                                 }
@@ -243,13 +1007,13 @@ fn edit_video_item(video_item: &mut VideoItem) -> Result<(), io::Error> {
                         print!("watch (true/false)? ");
                         stdout.flush()?;
                         let mut inp = String::new();
-                        stdin.read_line(&mut inp)?;
+                        read_line(&stdin, &mut inp)?;
                         match inp.trim() {
                             "true" => {
                                 print!("watch: ");
                                 stdout.flush()?;
                                 let mut watch = String::new();
-                                stdin.read_line(&mut watch)?;
+                                read_line(&stdin, &mut watch)?;
                                 if !watch.trim().is_empty() {
                                     video_item.site_data.watch = Some(watch.trim().to_string())
                                 }
@@ -274,42 +1038,33 @@ fn edit_video_item(video_item: &mut VideoItem) -> Result<(), io::Error> {
                         > ");
                 stdout.flush()?;
                 let mut inp = String::new();
-                stdin.read_line(&mut inp)?;
+                read_line(&stdin, &mut inp)?;
                 match inp.trim() {
                     "1" => loop {
-                        print!("status (virgin, partial, exhausted): ");
+                        print!("status (virgin, plan-to-watch, partial, rewatching[:N], on-hold, dropped, caught-up, completed): ");
                         stdout.flush()?;
                         let mut inp = String::new();
-                        stdin.read_line(&mut inp)?;
-                        match inp.trim() {
-                            "virgin" => {
-                                video_item.watch_data.status = WatchStatus::Virgin;
-                                break;
-                            },
-                            "partial" => {
-                                video_item.watch_data.status = WatchStatus::Partial;
-                                break;
-                            },
-                            "exhausted" => {
-                                video_item.watch_data.status = WatchStatus::Exhausted;
-                                break;
-                            },
-                            "" => break,
-                            _ => continue,
+                        read_line(&stdin, &mut inp)?;
+                        if inp.trim().is_empty() {
+                            break;
+                        }
+                        if let Ok(status) = inp.trim().parse::<WatchStatus>() {
+                            video_item.watch_data.status = status;
+                            break;
                         }
                     },
                     "2" => loop {
                         print!("position (true/false)? ");
                         stdout.flush()?;
                         let mut inp = String::new();
-                        stdin.read_line(&mut inp)?;
+                        read_line(&stdin, &mut inp)?;
                         match inp.trim() {
                             "true" => break {
                                 let season: u16 = loop {
                                     print!("season: ");
                                     stdout.flush()?;
                                     let mut inp = String::new();
-                                    stdin.read_line(&mut inp)?;
+                                    read_line(&stdin, &mut inp)?;
                                     let Ok(season) = inp.trim().parse::<u16>() else { continue; };
                                     break season;
                                 };
@@ -317,13 +1072,13 @@ fn edit_video_item(video_item: &mut VideoItem) -> Result<(), io::Error> {
                                     print!("episode (true/false)? ");
                                     stdout.flush()?;
                                     let mut inp = String::new();
-                                    stdin.read_line(&mut inp)?;
+                                    read_line(&stdin, &mut inp)?;
                                     match inp.trim() {
                                         "true" => break Some( loop {
                                             print!("episode: ");
                                             stdout.flush()?;
                                             let mut inp = String::new();
-                                            stdin.read_line(&mut inp)?;
+                                            read_line(&stdin, &mut inp)?;
                                             let Ok(episode) = inp.trim().parse::<u16>() else { continue; };
                                             break episode;
                                         }),
@@ -349,7 +1104,7 @@ fn edit_video_item(video_item: &mut VideoItem) -> Result<(), io::Error> {
                 print!("ongoing (true/false)? ");
                 stdout.flush()?;
                 let mut inp = String::new();
-                stdin.read_line(&mut inp)?;
+                read_line(&stdin, &mut inp)?;
                 match inp.trim() {
                     "true" => {
                         video_item.ongoing = true;
@@ -367,7 +1122,7 @@ fn edit_video_item(video_item: &mut VideoItem) -> Result<(), io::Error> {
                 print!("Enter updated date (yyyy-mm-dd): ");
                 stdout.flush()?;
                 let mut inp = String::new();
-                stdin.read_line(&mut inp)?;
+                read_line(&stdin, &mut inp)?;
                 if inp.trim().is_empty() {
                     break;
                 }
@@ -376,6 +1131,92 @@ fn edit_video_item(video_item: &mut VideoItem) -> Result<(), io::Error> {
                     break;
                 }
             },
+            "6" => loop {
+                println!("{:#?}", video_item.opinion);
+                print!("1. rating\n\
+                        2. season rating\n\
+                        3. review\n\
+                        > ");
+                stdout.flush()?;
+                let mut inp = String::new();
+                read_line(&stdin, &mut inp)?;
+                match inp.trim() {
+                    "1" => loop {
+                        print!("rating (none to clear): ");
+                        stdout.flush()?;
+                        let mut inp = String::new();
+                        read_line(&stdin, &mut inp)?;
+                        match inp.trim() {
+                            "" => break,
+                            "none" => {
+                                video_item.opinion.rating = None;
+                                break;
+                            },
+                            inp => if let Ok(rating) = scale.parse_rating(inp) {
+                                video_item.opinion.rating = Some(rating);
+                                break;
+                            },
+                        }
+                    },
+                    "2" => loop {
+                        print!("season: ");
+                        stdout.flush()?;
+                        let mut inp = String::new();
+                        read_line(&stdin, &mut inp)?;
+                        if inp.trim().is_empty() {
+                            break;
+                        }
+                        let Ok(season) = inp.trim().parse::<u16>() else { continue; };
+                        break loop {
+                            print!("season {season} rating (none to clear): ");
+                            stdout.flush()?;
+                            let mut inp = String::new();
+                            read_line(&stdin, &mut inp)?;
+                            match inp.trim() {
+                                "" => break,
+                                "none" => {
+                                    video_item.opinion.season_ratings.remove(&season);
+                                    break;
+                                },
+                                inp => if let Ok(rating) = scale.parse_rating(inp) {
+                                    video_item.opinion.season_ratings.insert(season, rating);
+                                    break;
+                                },
+                            }
+                        };
+                    },
+                    "3" => {
+                        print!("review (none to clear): ");
+                        stdout.flush()?;
+                        let mut review = String::new();
+                        read_line(&stdin, &mut review)?;
+                        match review.trim() {
+                            "" => (),
+                            "none" => video_item.opinion.review = None,
+                            review => video_item.opinion.review = Some(review.to_string()),
+                        }
+                    },
+                    "" => break,
+                    _ => continue,
+                }
+            },
+            "7" => loop {
+                print!("priority (1 first, none to clear): ");
+                stdout.flush()?;
+                let mut inp = String::new();
+                read_line(&stdin, &mut inp)?;
+                match inp.trim() {
+                    "" => break,
+                    "none" => {
+                        video_item.priority = None;
+                        break;
+                    },
+                    inp => if let Ok(priority) = inp.parse::<u16>() {
+                        video_item.priority = Some(priority);
+                        break;
+                    },
+                }
+            },
             "" => break,
             _ => continue,
         }
@@ -383,91 +1224,119 @@ fn edit_video_item(video_item: &mut VideoItem) -> Result<(), io::Error> {
     Ok(())
 }
 
-fn create_video_item() -> Result<VideoItem, io::Error> {
+/// prompts for every required field that was not given on the command line
+fn create_video_item(fields: NewItemFields, scale: RatingScale) -> Result<VideoItem, io::Error> {
     let stdin = io::stdin();
     let mut stdout = io::stdout();
+    // nobody is there to answer prompts, e.g. in a script
+    if !stdin.is_terminal() {
+        let missing = fields.missing();
+        if !missing.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("missing {}", missing.join(", "))));
+        }
+    }
+    let rating = match fields.rating {
+        Some(rating) => Some(scale.parse_rating(&rating).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?),
+        None => None,
+    };
     let work: VideoWork = {
-        let title: String = loop {
-            print!("title: ");
-            stdout.flush()?;
-            let mut title = String::new();
-            stdin.read_line(&mut title)?;
-            if title.trim().is_empty() {
-                continue;
-            }
-            break title.trim().to_string();
+        let title: String = match fields.title {
+            Some(title) => title,
+            None => loop {
+                print!("title: ");
+                stdout.flush()?;
+                let mut title = String::new();
+                read_line(&stdin, &mut title)?;
+                if title.trim().is_empty() {
+                    continue;
+                }
+                break title.trim().to_string();
+            },
         };
         // println!("VideoWork {{\n\ttitle: {title}\n}}");
-        let year: u16 = loop {
-            print!("year: ");
-            stdout.flush()?;
-            let mut inp = String::new();
-            stdin.read_line(&mut inp)?;
-            let Ok(year) = inp.trim().parse::<u16>() else { continue; };
-            break year;
+        let year: u16 = match fields.year {
+            Some(year) => year,
+            None => loop {
+                print!("year: ");
+                stdout.flush()?;
+                let mut inp = String::new();
+                read_line(&stdin, &mut inp)?;
+                let Ok(year) = inp.trim().parse::<u16>() else { continue; };
+                break year;
+            },
         };
         // println!("VideoWork {{\n\ttitle: {title}\n\tyear: {year}\n}}");
-        let medium: VideoWorkMedium = loop {
-            print!("medium (movie, tvshow, anime): ");
-            stdout.flush()?;
-            let mut inp = String::new();
-            stdin.read_line(&mut inp)?;
-            match inp.trim() {
-                "movie" => break VideoWorkMedium::Movie,
-                "tvshow" => break VideoWorkMedium::TvShow,
-                "anime" => break VideoWorkMedium::Anime,
-                _ => continue,
-            }
+        let medium: VideoWorkMedium = match fields.medium {
+            Some(medium) => medium,
+            None => loop {
+                print!("medium (movie, tvshow, anime): ");
+                stdout.flush()?;
+                let mut inp = String::new();
+                read_line(&stdin, &mut inp)?;
+                match inp.trim() {
+                    "movie" => break VideoWorkMedium::Movie,
+                    "tvshow" => break VideoWorkMedium::TvShow,
+                    "anime" => break VideoWorkMedium::Anime,
+                    _ => continue,
+                }
+            },
         };
         // println!("VideoWork {{\n\ttitle: {title}\n\tyear: {year}\n\tmedium: {medium:?}\n}}");
         VideoWork {
             title,
             year,
             medium,
+            episodes: fields.episodes,
         }
     };
     let site_data: SiteData = {
-        SiteData { tracker: None, watch: None }
+        SiteData { tracker: fields.tracker, watch: fields.watch }
     };
     let watch_data: WatchData = {
         match work.medium {
             VideoWorkMedium::Movie => {
-                let status = loop {
-                    print!("watched (true/false)? ");
-                    stdout.flush()?;
-                    let mut inp = String::new();
-                    stdin.read_line(&mut inp)?;
-                    match inp.trim() {
-                        "true" => break WatchStatus::Exhausted,
-                        "false" => break WatchStatus::Virgin,
-                        _ => continue,
-                    }
+                if fields.position.is_some() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, "movies have no watch position"));
+                }
+                let status = match fields.status {
+                    Some(status) => status,
+                    None => loop {
+                        print!("watched (true/false)? ");
+                        stdout.flush()?;
+                        let mut inp = String::new();
+                        read_line(&stdin, &mut inp)?;
+                        match inp.trim() {
+                            "true" => break WatchStatus::Completed,
+                            "false" => break WatchStatus::Virgin,
+                            _ => continue,
+                        }
+                    },
                 };
                 WatchData { status, position: None }
             }
             VideoWorkMedium::TvShow |
             VideoWorkMedium::Anime => {
-                let status: WatchStatus = loop {
-                    print!("watch status (virgin, partial, exhausted): ");
-                    stdout.flush()?;
-                    let mut inp = String::new();
-                    stdin.read_line(&mut inp)?;
-                    match inp.trim() {
-                        "virgin" => break WatchStatus::Virgin,
-                        "partial" => break WatchStatus::Partial,
-                        "exhausted" => break WatchStatus::Exhausted,
-                        _ => continue,
-                    }
+                let status: WatchStatus = match fields.status {
+                    Some(status) => status,
+                    None => loop {
+                        print!("watch status (virgin, plan-to-watch, partial, rewatching[:N], on-hold, dropped, caught-up, completed): ");
+                        stdout.flush()?;
+                        let mut inp = String::new();
+                        read_line(&stdin, &mut inp)?;
+                        let Ok(status) = inp.trim().parse::<WatchStatus>() else { continue; };
+                        break status;
+                    },
                 };
-                let position = match status {
-                    WatchStatus::Virgin => None,
-                    WatchStatus::Partial |
-                    WatchStatus::Exhausted => Some({
+                let position = match (status.started(), fields.position) {
+                    (false, Some(_)) => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("a {status} series has no watch position"))),
+                    (true, Some(position)) => Some(position),
+                    (false, None) => None,
+                    (true, None) => Some({
                         let season: u16 = loop {
                             print!("season: ");
                             stdout.flush()?;
                             let mut inp = String::new();
-                            stdin.read_line(&mut inp)?;
+                            read_line(&stdin, &mut inp)?;
                             let Ok(season) = inp.trim().parse::<u16>() else { continue; };
                             break season;
                         };
@@ -475,13 +1344,13 @@ fn create_video_item() -> Result<VideoItem, io::Error> {
                             print!("episode (true/false)? ");
                             stdout.flush()?;
                             let mut inp = String::new();
-                            stdin.read_line(&mut inp)?;
+                            read_line(&stdin, &mut inp)?;
                             match inp.trim() {
                                 "true" => break Some( loop {
                                     print!("episode: ");
                                     stdout.flush()?;
                                     let mut inp = String::new();
-                                    stdin.read_line(&mut inp)?;
+                                    read_line(&stdin, &mut inp)?;
                                     let Ok(episode) = inp.trim().parse::<u16>() else { continue; };
                                     break episode;
                                 }),
@@ -498,137 +1367,53 @@ fn create_video_item() -> Result<VideoItem, io::Error> {
     };
     let ongoing: bool = {
         match work.medium {
-            VideoWorkMedium::Movie => false,
+            VideoWorkMedium::Movie => match fields.ongoing {
+                Some(true) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "movies cannot be ongoing")),
+                Some(false) | None => false,
+            },
             VideoWorkMedium::TvShow |
-            VideoWorkMedium::Anime => loop {
-                print!("ongoing (true/false)? ");
-                stdout.flush()?;
-                let mut inp = String::new();
-                stdin.read_line(&mut inp)?;
-                match inp.trim() {
-                    "true" => break true,
-                    "false" => break false,
-                    _ => continue,
-                }
-            }
+            VideoWorkMedium::Anime => match fields.ongoing {
+                Some(ongoing) => ongoing,
+                None => loop {
+                    print!("ongoing (true/false)? ");
+                    stdout.flush()?;
+                    let mut inp = String::new();
+                    read_line(&stdin, &mut inp)?;
+                    match inp.trim() {
+                        "true" => break true,
+                        "false" => break false,
+                        _ => continue,
+                    }
+                },
+            },
         }
     };
     let updated: NaiveDate = {
         chrono::Local::now().date_naive()
     };
-    Ok(VideoItem { work, site_data, watch_data, ongoing, updated })
-}
-
-struct FileVideoItems<'a>(BufReader<&'a File>);
-
-impl<'a> Iterator for FileVideoItems<'a> {
-    type Item = VideoItem;
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            let mut line = String::new();
-            match self.0.read_line(&mut line) {
-                Err(e) => {
-                    eprintln!("error reading line from file {e:?}");
-                    continue;
-                },
-                Ok(0) => return None,
-                Ok(_) => (),
-            }
-            match ron::from_str(&line) {
-                Err(e) => {
-                    eprintln!("error parsing RON from line \"{line}\": {e:?}");
-                    continue;
-                },
-                Ok(vi) => return Some(vi),
-            }
-        }
-    }
-}
-
-impl<'a> FileVideoItems<'a> {
-    fn new(f: &'a File) -> Self {
-        Self(BufReader::new(f))
-    }
-}
-
-#[derive(Debug)]
-enum Error {
-    IO(io::Error),
-    RON(ron::Error),
-}
-
-impl From<io::Error> for Error {
-    fn from(error: io::Error) -> Self {
-        Self::IO(error)
-    }
-}
-
-impl From<ron::Error> for Error {
-    fn from(error: ron::Error) -> Self {
-        Self::RON(error)
-    }
-}
-
-fn write_video_item_to_file<'a>(f: &'a mut File, vi: &VideoItem) -> Result<(), Error> {
-    let mut writer = BufWriter::new(f);
-    let mut s = ron::to_string(vi)?;
-    s.push('\n');
-    writer.write_all(s.as_bytes())?;
-    Ok(())
-}
-
-fn edit_video_items_by_name_to_temp_file(iter: impl Iterator<Item = VideoItem>, name: &str, filepath: &Path) -> Result<bool, io::Error> {
-    let mut f = OpenOptions::new()
-        .create_new(true)
-        .append(true)
-        .open(filepath)?;
-    let mut encountered_error = false;
-    for mut video_item in iter {
-        if video_item.work.title == name {
-            match edit_video_item(&mut video_item) {
-                Ok(()) => (),
-                Err(e) => {
-                    encountered_error = true;
-                    eprintln!("error while editing {e:?}");
-                },
-            }
-        }
-        match write_video_item_to_file(&mut f, &video_item) {
-            Ok(()) => (),
-            Err(Error::IO(e)) => {
-                encountered_error = true;
-                eprintln!("error writing to file {e:?}");
-            },
-            Err(Error::RON(e)) => {
-                encountered_error = true;
-                eprintln!("error stringifying work {e:?} {video_item:?}");
-            },
-        }
-    }
-    Ok(encountered_error)
-}
-
-fn remove_video_items_by_name_to_temp_file(iter: impl Iterator<Item = VideoItem>, name: &str, filepath: &Path) -> Result<bool, io::Error> {
-    let mut f = OpenOptions::new()
-        .create_new(true)
-        .append(true)
-        .open(filepath)?;
-    let mut encountered_error = false;
-    for video_item in iter {
-        match video_item.work.title == name {
-            true => println!("removed: {video_item:#?}"),
-            false => match write_video_item_to_file(&mut f, &video_item) {
-                Ok(()) => (),
-                Err(Error::IO(e)) => {
-                    encountered_error = true;
-                    eprintln!("error writing to file {e:?}");
-                },
-                Err(Error::RON(e)) => {
-                    encountered_error = true;
-                    eprintln!("error stringifying work {e:?} {video_item:?}");
-                },
-            },
-        }
-    }
-    Ok(encountered_error)
+    let history = vec![HistoryEvent { date: updated, kind: HistoryEventKind::Added }];
+    let opinion = Opinion { rating, season_ratings: Default::default(), review: fields.review };
+    let tags = fields.tags.into_iter().collect();
+    let mut video_item = VideoItem {
+        id: 0,
+        work,
+        site_data,
+        watch_data,
+        opinion,
+        tags,
+        priority: fields.priority,
+        ongoing,
+        updated,
+        history,
+    };
+    // the status is asked before whether the series is ongoing
+    video_item.reconcile_status();
+    video_item.catch_up();
+    // only starting or having completed it is worth recording, not e.g. planning to watch it
+    let status = match video_item.watch_data.status.started() {
+        true => WatchStatus::Virgin,
+        false => video_item.watch_data.status.clone(),
+    };
+    video_item.record_changes(&WatchData { status, position: None }, updated);
+    Ok(video_item)
 }
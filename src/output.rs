@@ -0,0 +1,463 @@
+use std::{collections::BTreeMap, io::{self, Write as _}};
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use unicode_width::{UnicodeWidthChar as _, UnicodeWidthStr as _};
+use watchlist::{Count, HistoryEvent, RatingScale, Stats, VideoItem, VideoWorkMedium};
+
+/// the longest bar in a histogram
+const BAR_WIDTH: usize = 40;
+
+/// how listings are printed; a table unless `--long` or `--format` is given
+#[derive(Args)]
+pub struct Output {
+    /// list items in full detail instead of as a table
+    #[arg(short, long, global = true)]
+    long: bool,
+
+    /// print listings in a machine readable format instead
+    #[arg(long, global = true, value_enum)]
+    format: Option<Format>,
+}
+
+#[derive(Clone, ValueEnum)]
+enum Format {
+    /// a single JSON array
+    Json,
+    /// one JSON object per line
+    Jsonl,
+    /// one item per line, as in the data file
+    Ron,
+    /// flattened, with a header row; items also get their MyAnimeList and AniList statuses
+    Csv,
+}
+
+impl Output {
+    /// tables show ratings on `scale`; the machine readable formats always have them out of 10
+    pub fn print_video_items(&self, video_items: &[VideoItem], scale: RatingScale) {
+        self.print(video_items, COLUMNS, scale);
+    }
+
+    /// like `print_video_items`, but tables show the next episode and priority instead
+    pub fn print_up_next(&self, video_items: &[VideoItem], scale: RatingScale) {
+        self.print(video_items, UP_NEXT_COLUMNS, scale);
+    }
+
+    /// a readable summary unless `--format` is given
+    pub fn print_stats(&self, stats: &Stats) {
+        match &self.format {
+            None => print_summary(stats),
+            Some(format) => if let Err(e) = print_formatted_one(stats, format) {
+                eprintln!("error printing {e}");
+            },
+        }
+    }
+
+    /// how far along each item is; the counts are empty or null where episode counts are unknown
+    pub fn print_remaining(&self, video_items: &[VideoItem]) {
+        match &self.format {
+            None => for video_item in video_items {
+                match (video_item.progress(), video_item.percent_watched()) {
+                    (Some((watched, total)), Some(percent)) => println!(
+                        "{}: {} of {total} episodes left ({percent}% watched)",
+                        video_item.work.title,
+                        total - watched,
+                    ),
+                    _ if video_item.work.medium == VideoWorkMedium::Movie => println!("{}: a movie", video_item.work.title),
+                    _ => println!("{}: episode counts unknown; set them with `watchlist edit --set episodes=...`", video_item.work.title),
+                }
+            },
+            Some(format) => {
+                let remaining: Vec<Remaining> = video_items.iter()
+                    .map(|vi| Remaining {
+                        id: vi.id,
+                        title: &vi.work.title,
+                        medium: &vi.work.medium,
+                        watched: vi.progress().map(|(watched, _)| watched),
+                        total: vi.progress().map(|(_, total)| total),
+                        remaining: vi.remaining(),
+                        percent_watched: vi.percent_watched(),
+                    })
+                    .collect();
+                if let Err(e) = print_formatted(&remaining, format) {
+                    eprintln!("error printing {e}");
+                }
+            },
+        }
+    }
+
+    /// every tag in use with how many items have it, in tag order
+    pub fn print_tag_counts(&self, counts: &BTreeMap<String, usize>) {
+        match &self.format {
+            None => for (tag, count) in counts {
+                println!("{tag} ({count})");
+            },
+            Some(format) => {
+                let counts: Vec<TagCount> = counts.iter().map(|(tag, &count)| TagCount { tag, count }).collect();
+                if let Err(e) = print_formatted(&counts, format) {
+                    eprintln!("error printing {e}");
+                }
+            },
+        }
+    }
+
+    /// the tags of each item
+    pub fn print_item_tags(&self, video_items: &[VideoItem]) {
+        match &self.format {
+            None => for video_item in video_items {
+                let tags: Vec<&str> = video_item.tags.iter().map(String::as_str).collect();
+                println!("{}: {}", describe(video_item), tags.join(" "));
+            },
+            Some(format) => {
+                let item_tags: Vec<ItemTags> = video_items.iter()
+                    .map(|vi| ItemTags { id: vi.id, title: &vi.work.title, tags: vi.tags.iter().map(String::as_str).collect() })
+                    .collect();
+                if let Err(e) = print_formatted(&item_tags, format) {
+                    eprintln!("error printing {e}");
+                }
+            },
+        }
+    }
+
+    /// every item's events, oldest first, under a line telling the item apart
+    pub fn print_history(&self, video_items: &[VideoItem]) {
+        match &self.format {
+            None => for video_item in video_items {
+                println!("{}", describe(video_item));
+                if video_item.history.is_empty() {
+                    println!("  no history recorded; last updated {}", video_item.updated);
+                }
+                for event in &video_item.history {
+                    println!("  {event}");
+                }
+            },
+            Some(format) => {
+                let histories: Vec<History> = video_items.iter()
+                    .map(|vi| History { id: vi.id, title: &vi.work.title, history: &vi.history })
+                    .collect();
+                if let Err(e) = print_formatted(&histories, format) {
+                    eprintln!("error printing {e}");
+                }
+            },
+        }
+    }
+
+    fn print(&self, video_items: &[VideoItem], columns: &[Column], scale: RatingScale) {
+        match &self.format {
+            None => print_table(video_items, columns, self.long, scale),
+            Some(format) => if let Err(e) = print_formatted(video_items, format) {
+                eprintln!("error printing {e}");
+            },
+        }
+    }
+}
+
+fn print_formatted<T: Serialize + CsvRows>(items: &[T], format: &Format) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stdout = io::stdout().lock();
+    match format {
+        Format::Json => writeln!(stdout, "{}", serde_json::to_string_pretty(items)?)?,
+        Format::Jsonl => for item in items {
+            writeln!(stdout, "{}", serde_json::to_string(item)?)?;
+        },
+        Format::Ron => for item in items {
+            writeln!(stdout, "{}", ron::to_string(item)?)?;
+        },
+        Format::Csv => {
+            writeln!(stdout, "{}", csv_line(T::HEADER.iter().copied()))?;
+            for row in items.iter().flat_map(CsvRows::csv_rows) {
+                writeln!(stdout, "{}", csv_line(row.iter().map(String::as_str)))?;
+            }
+        },
+    }
+    Ok(())
+}
+
+/// a single value rather than a list, e.g. a JSON object rather than an array
+fn print_formatted_one<T: Serialize + CsvRows>(item: &T, format: &Format) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        Format::Json => writeln!(io::stdout().lock(), "{}", serde_json::to_string_pretty(item)?)?,
+        Format::Jsonl | Format::Ron | Format::Csv => print_formatted(std::slice::from_ref(item), format)?,
+    }
+    Ok(())
+}
+
+/// a flat CSV representation; optional fields are empty when absent
+trait CsvRows {
+    const HEADER: &[&str];
+    fn csv_rows(&self) -> Vec<Vec<String>>;
+}
+
+impl CsvRows for VideoItem {
+    const HEADER: &[&str] = &["id", "title", "year", "medium", "episodes", "tracker", "watch", "status", "mal_status", "anilist_status", "season", "episode", "rating", "season_ratings", "review", "tags", "priority", "ongoing", "updated"];
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        let position = self.watch_data.position.as_ref();
+        vec![vec![
+            self.id.to_string(),
+            self.work.title.clone(),
+            self.work.year.to_string(),
+            self.work.medium.to_string(),
+            self.work.episodes.iter().map(ToString::to_string).collect::<Vec<_>>().join(","),
+            self.site_data.tracker.clone().unwrap_or_default(),
+            self.site_data.watch.clone().unwrap_or_default(),
+            self.watch_data.status.to_string(),
+            self.watch_data.status.mal().to_string(),
+            self.watch_data.status.anilist().to_string(),
+            position.map(|p| p.season.to_string()).unwrap_or_default(),
+            position.and_then(|p| p.episode).map(|e| e.to_string()).unwrap_or_default(),
+            self.opinion.rating.map(|r| r.to_string()).unwrap_or_default(),
+            self.opinion.season_ratings.iter().map(|(season, rating)| format!("{season}:{rating}")).collect::<Vec<_>>().join(" "),
+            self.opinion.review.clone().unwrap_or_default(),
+            self.tags.iter().cloned().collect::<Vec<_>>().join(" "),
+            self.priority.map(|p| p.to_string()).unwrap_or_default(),
+            self.ongoing.to_string(),
+            self.updated.to_string(),
+        ]]
+    }
+}
+
+/// one row per figure, e.g. `medium,anime,12`
+impl CsvRows for Stats {
+    const HEADER: &[&str] = &["section", "key", "value"];
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        let row = |section: &str, key: &str, value: String| vec![section.to_string(), key.to_string(), value];
+        let counts = |section: &'static str, counts: &[Count]| counts.iter()
+            .map(|count| row(section, &count.key, count.count.to_string()))
+            .collect::<Vec<_>>();
+        let mut rows = vec![row("items", "", self.items.to_string())];
+        rows.extend(counts("medium", &self.by_medium));
+        rows.extend(counts("status", &self.by_status));
+        rows.push(row("series", "ongoing", self.ongoing.to_string()));
+        rows.push(row("series", "finished", self.finished.to_string()));
+        rows.extend(counts("updated", &self.updated_per_month));
+        rows.extend(self.oldest_partial.iter().map(|untouched| row("oldest_partial", &untouched.title, untouched.updated.to_string())));
+        rows.extend(counts("year", &self.by_year));
+        rows
+    }
+}
+
+#[derive(Serialize)]
+struct Remaining<'a> {
+    id: u32,
+    title: &'a str,
+    medium: &'a VideoWorkMedium,
+    watched: Option<u32>,
+    total: Option<u32>,
+    remaining: Option<u32>,
+    percent_watched: Option<u32>,
+}
+
+impl CsvRows for Remaining<'_> {
+    const HEADER: &'static [&'static str] = &["id", "title", "medium", "watched", "total", "remaining", "percent_watched"];
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        let count = |count: Option<u32>| count.map(|c| c.to_string()).unwrap_or_default();
+        vec![vec![
+            self.id.to_string(),
+            self.title.to_string(),
+            self.medium.to_string(),
+            count(self.watched),
+            count(self.total),
+            count(self.remaining),
+            count(self.percent_watched),
+        ]]
+    }
+}
+
+#[derive(Serialize)]
+struct TagCount<'a> {
+    tag: &'a str,
+    count: usize,
+}
+
+impl CsvRows for TagCount<'_> {
+    const HEADER: &'static [&'static str] = &["tag", "count"];
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        vec![vec![self.tag.to_string(), self.count.to_string()]]
+    }
+}
+
+#[derive(Serialize)]
+struct ItemTags<'a> {
+    id: u32,
+    title: &'a str,
+    tags: Vec<&'a str>,
+}
+
+/// the tags space separated, as in listings
+impl CsvRows for ItemTags<'_> {
+    const HEADER: &'static [&'static str] = &["id", "title", "tags"];
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        vec![vec![self.id.to_string(), self.title.to_string(), self.tags.join(" ")]]
+    }
+}
+
+#[derive(Serialize)]
+struct History<'a> {
+    id: u32,
+    title: &'a str,
+    history: &'a [HistoryEvent],
+}
+
+/// one row per event, e.g. `9,Dune,2024-03-01,completed`
+impl CsvRows for History<'_> {
+    const HEADER: &'static [&'static str] = &["id", "title", "date", "event"];
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.history.iter()
+            .map(|event| vec![self.id.to_string(), self.title.to_string(), event.date.to_string(), event.kind.to_string()])
+            .collect()
+    }
+}
+
+fn print_summary(stats: &Stats) {
+    println!("{} items", stats.items);
+    let inline = |counts: &[Count]| counts.iter()
+        .map(|count| format!("{} {}", count.count, count.key))
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!("by medium: {}", inline(&stats.by_medium));
+    println!("by status: {}", inline(&stats.by_status));
+    println!("series: {} ongoing, {} finished", stats.ongoing, stats.finished);
+    if !stats.oldest_partial.is_empty() {
+        println!("\nuntouched the longest while partially watched:");
+        for untouched in &stats.oldest_partial {
+            println!("  {}  {} [id {}]", untouched.updated, untouched.title, untouched.id);
+        }
+    }
+    println!("\nupdated per month:");
+    print_histogram(&stats.updated_per_month);
+    println!("\nby release year:");
+    print_histogram(&stats.by_year);
+}
+
+fn print_histogram(counts: &[Count]) {
+    let max = counts.iter().map(|count| count.count).max().unwrap_or(0);
+    let key_width = counts.iter().map(|count| count.key.width()).max().unwrap_or(0);
+    for count in counts {
+        // every count gets at least some bar
+        let bar = "█".repeat((count.count * BAR_WIDTH).div_ceil(max));
+        println!("  {}  {bar} {}", pad(&count.key, key_width, false), count.count);
+    }
+}
+
+/// quotes fields containing separators, quotes or line breaks (RFC 4180)
+fn csv_line<'a>(fields: impl Iterator<Item = &'a str>) -> String {
+    fields
+        .map(|field| match field.contains([',', '"', '\n', '\r']) {
+            true => format!("\"{}\"", field.replace('"', "\"\"")),
+            false => field.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// an item in one line, to tell it apart from others with a similar title
+pub fn describe(video_item: &VideoItem) -> String {
+    format!("{} ({}, {}) [id {}]", video_item.work.title, video_item.work.year, video_item.work.medium, video_item.id)
+}
+
+/// where watching an item stands in one line, as in a listing, e.g. `Frieren: S02E05 (partial)`
+pub fn summary(video_item: &VideoItem) -> String {
+    let position = match &video_item.watch_data.position {
+        Some(position) => format!(": {position}"),
+        None => String::new(),
+    };
+    let progress = match (video_item.progress(), video_item.percent_watched()) {
+        (Some((watched, total)), Some(percent)) => format!(", {watched}/{total} {percent}%"),
+        _ => String::new(),
+    };
+    format!("{}{position} ({}{progress})", video_item.work.title, video_item.watch_data.status)
+}
+
+/// a table column; the title column shrinks to fit the terminal, the others never do
+struct Column {
+    header: &'static str,
+    right_aligned: bool,
+    cell: fn(&VideoItem, RatingScale) -> String,
+}
+
+const COLUMNS: &[Column] = &[
+    Column { header: "ID", right_aligned: true, cell: |vi, _| vi.id.to_string() },
+    Column { header: "TITLE", right_aligned: false, cell: |vi, _| vi.work.title.clone() },
+    Column { header: "YEAR", right_aligned: true, cell: |vi, _| vi.work.year.to_string() },
+    Column { header: "MEDIUM", right_aligned: false, cell: |vi, _| vi.work.medium.to_string() },
+    Column { header: "STATUS", right_aligned: false, cell: |vi, _| vi.watch_data.status.to_string() },
+    Column { header: "POSITION", right_aligned: false, cell: |vi, _| vi.watch_data.position.as_ref().map(ToString::to_string).unwrap_or_default() },
+    Column { header: "PROGRESS", right_aligned: true, cell: |vi, _| match (vi.progress(), vi.percent_watched()) {
+        (Some((watched, total)), Some(percent)) => format!("{watched}/{total} {percent:>3}%"),
+        _ => String::new(),
+    } },
+    Column { header: "RATING", right_aligned: false, cell: |vi, scale| vi.opinion.rating.map(|r| scale.format_rating(r)).unwrap_or_default() },
+    Column { header: "UPDATED", right_aligned: false, cell: |vi, _| vi.updated.to_string() },
+];
+
+const UP_NEXT_COLUMNS: &[Column] = &[
+    Column { header: "ID", right_aligned: true, cell: |vi, _| vi.id.to_string() },
+    Column { header: "TITLE", right_aligned: false, cell: |vi, _| vi.work.title.clone() },
+    Column { header: "MEDIUM", right_aligned: false, cell: |vi, _| vi.work.medium.to_string() },
+    Column { header: "STATUS", right_aligned: false, cell: |vi, _| vi.watch_data.status.to_string() },
+    Column { header: "NEXT", right_aligned: false, cell: |vi, _| vi.next_episode().as_ref().map(ToString::to_string).unwrap_or_default() },
+    Column { header: "PRIORITY", right_aligned: true, cell: |vi, _| vi.priority.map(|p| p.to_string()).unwrap_or_default() },
+    Column { header: "UPDATED", right_aligned: false, cell: |vi, _| vi.updated.to_string() },
+];
+
+/// the same in every list of columns
+const TITLE_COLUMN: usize = 1;
+
+fn print_table(video_items: &[VideoItem], columns: &[Column], long: bool, scale: RatingScale) {
+    if long {
+        for video_item in video_items {
+            println!("{video_item:#?}");
+        }
+        return;
+    }
+    let header: Vec<String> = columns.iter().map(|column| column.header.to_string()).collect();
+    let rows: Vec<Vec<String>> = video_items.iter()
+        .map(|vi| columns.iter().map(|column| (column.cell)(vi, scale)).collect())
+        .collect();
+    // in terminal columns, as e.g. CJK characters take two
+    let mut widths: Vec<usize> = header.iter().map(|cell| cell.width()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+    // the title gets whatever the other columns leave of the terminal width
+    if let Some((terminal_size::Width(terminal_width), _)) = terminal_size::terminal_size() {
+        let others: usize = widths.iter().enumerate()
+            .filter(|(i, _)| *i != TITLE_COLUMN)
+            .map(|(_, width)| width + 2)
+            .sum();
+        let available = (terminal_width as usize).saturating_sub(others).max(header[TITLE_COLUMN].len());
+        widths[TITLE_COLUMN] = widths[TITLE_COLUMN].min(available);
+    }
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row.iter().zip(columns).zip(&widths)
+            .map(|((cell, column), &width)| pad(&truncate(cell, width), width, column.right_aligned))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+}
+
+/// shortens `s` to at most `width` terminal columns, marking a cut with an ellipsis
+fn truncate(s: &str, width: usize) -> String {
+    if s.width() <= width {
+        return s.to_string();
+    }
+    let mut used = 0;
+    let mut truncated: String = s.chars()
+        .take_while(|c| {
+            used += c.width().unwrap_or(0);
+            used < width
+        })
+        .collect();
+    truncated.push('…');
+    truncated
+}
+
+/// fills `s` up to `width` terminal columns with spaces; `format!` padding counts characters instead
+fn pad(s: &str, width: usize, right_aligned: bool) -> String {
+    let fill = " ".repeat(width.saturating_sub(s.width()));
+    match right_aligned {
+        true => format!("{fill}{s}"),
+        false => format!("{s}{fill}"),
+    }
+}
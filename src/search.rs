@@ -0,0 +1,82 @@
+use unicode_normalization::{UnicodeNormalization as _, char::is_combining_mark};
+
+/// lowercased, without accents and with runs of whitespace collapsed
+pub(crate) fn normalize(s: &str) -> String {
+    s.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// how well `title` matches the normalized `query`, lower is better; `None` if it does not match
+///
+/// 0 for the same title, 1 for a prefix, 2 for a substring starting a word, 3 for any other
+/// substring, and 10 plus the number of typos for a part of the title that is close enough
+pub(crate) fn title_score(query: &str, title: &str) -> Option<u32> {
+    let title = normalize(title);
+    if query.is_empty() {
+        return None;
+    }
+    if title == query {
+        return Some(0);
+    }
+    if title.starts_with(query) {
+        return Some(1);
+    }
+    if title.match_indices(query).any(|(i, _)| !title[..i].ends_with(char::is_alphanumeric)) {
+        return Some(2);
+    }
+    if title.contains(query) {
+        return Some(3);
+    }
+    let query: Vec<char> = query.chars().collect();
+    let allowed_typos = match query.len() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    };
+    if allowed_typos == 0 {
+        return None;
+    }
+    // compare against every part of the title about as long as the query
+    let title: Vec<char> = title.chars().collect();
+    let mut best = None;
+    for start in 0..title.len() {
+        for len in query.len() - 1..=query.len() + 1 {
+            let Some(window) = title.get(start..start + len) else { break; };
+            let distance = typo_distance(&query, window);
+            if distance <= allowed_typos && best.is_none_or(|best| distance < best) {
+                best = Some(distance);
+            }
+        }
+    }
+    best.map(|distance| 10 + distance as u32)
+}
+
+/// edit distance where swapping two neighbouring characters counts as a single typo
+/// (optimal string alignment)
+fn typo_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
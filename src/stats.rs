@@ -0,0 +1,73 @@
+use std::{collections::BTreeMap, fmt};
+use chrono::NaiveDate;
+use crate::{VideoItem, VideoWorkMedium, WatchStatus};
+
+/// how many of the oldest untouched partially watched items `Stats` keeps
+const OLDEST_PARTIAL: usize = 5;
+
+/// an overview of a watchlist
+#[derive(Debug, serde::Serialize)]
+pub struct Stats {
+    pub items: usize,
+    /// in `VideoWorkMedium` order
+    pub by_medium: Vec<Count>,
+    /// in `WatchStatus` order, with every rewatch counted as `rewatching`
+    pub by_status: Vec<Count>,
+    /// series still coming out and series that have finished; movies are neither
+    pub ongoing: usize,
+    pub finished: usize,
+    /// by month of `updated` (yyyy-mm), oldest first
+    pub updated_per_month: Vec<Count>,
+    /// the partially watched items updated longest ago, oldest first
+    pub oldest_partial: Vec<Untouched>,
+    /// by release year, earliest first
+    pub by_year: Vec<Count>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct Count {
+    pub key: String,
+    pub count: usize,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct Untouched {
+    pub id: u32,
+    pub title: String,
+    pub updated: NaiveDate,
+}
+
+impl Stats {
+    pub fn new(video_items: &[VideoItem]) -> Self {
+        let series = || video_items.iter().filter(|vi| !matches!(vi.work.medium, VideoWorkMedium::Movie));
+        let mut partial: Vec<&VideoItem> = video_items.iter()
+            .filter(|vi| vi.watch_data.status == WatchStatus::Partial)
+            .collect();
+        partial.sort_by_key(|vi| vi.updated);
+        Self {
+            items: video_items.len(),
+            by_medium: counts(video_items.iter().map(|vi| vi.work.medium.clone())),
+            by_status: counts(video_items.iter().map(|vi| match vi.watch_data.status {
+                WatchStatus::Rewatching(_) => WatchStatus::Rewatching(1),
+                ref status => status.clone(),
+            })),
+            ongoing: series().filter(|vi| vi.ongoing).count(),
+            finished: series().filter(|vi| !vi.ongoing).count(),
+            updated_per_month: counts(video_items.iter().map(|vi| vi.updated.format("%Y-%m").to_string())),
+            oldest_partial: partial.into_iter()
+                .take(OLDEST_PARTIAL)
+                .map(|vi| Untouched { id: vi.id, title: vi.work.title.clone(), updated: vi.updated })
+                .collect(),
+            by_year: counts(video_items.iter().map(|vi| vi.work.year)),
+        }
+    }
+}
+
+/// how often each key occurs, in key order
+fn counts<K: Ord + fmt::Display>(keys: impl Iterator<Item = K>) -> Vec<Count> {
+    let mut counts: BTreeMap<K, usize> = BTreeMap::new();
+    for key in keys {
+        *counts.entry(key).or_default() += 1;
+    }
+    counts.into_iter().map(|(key, count)| Count { key: key.to_string(), count }).collect()
+}